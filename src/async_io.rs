@@ -0,0 +1,87 @@
+//! Module with async counterparts of reading and writing functions.
+//!
+//! Input is read and output is written without blocking runtime, records are parsed and serialized in memory
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    FileFormat, Record,
+    error::{Result, YpbankError},
+    read_all_records_from_slice, write_all_records,
+};
+
+/// Read all records in given format from async reader
+pub async fn read_all_records_async<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    input_format: FileFormat,
+) -> Result<Vec<Record>> {
+    let mut buffer = vec![];
+    reader.read_to_end(&mut buffer).await?;
+    read_all_records_from_slice(&buffer, input_format)
+}
+
+/// Write all records in given format to async writer
+pub async fn write_all_records_async<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    output_format: FileFormat,
+    records: &[Record],
+) -> Result<()> {
+    let mut buffer = vec![];
+    write_all_records(&mut buffer, output_format, records)?;
+    writer
+        .write_all(&buffer)
+        .await
+        .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+    writer
+        .flush()
+        .await
+        .map_err(|e| YpbankError::WriteError(e.to_string()))
+}
+
+// Tests need `rt` and `macros` features of tokio, which only dev-dependency enables
+#[cfg(test)]
+mod tests {
+    #![allow(unused_imports)]
+    use crate::{RecordStatus, RecordType};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_round_trip() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                "Payment for services, invoice #123".to_string(),
+            ),
+        ];
+
+        for format in FileFormat::all() {
+            // Small buffer makes both sides wait for each other
+            let (mut writer, mut reader) = tokio::io::duplex(16);
+            let write = async {
+                let result = write_all_records_async(&mut writer, format.clone(), &records).await;
+                drop(writer);
+                result
+            };
+            let (written, read) =
+                tokio::join!(write, read_all_records_async(&mut reader, format.clone()));
+
+            written.unwrap_or_else(|e| panic!("{format} should write successfully: {e}"));
+            assert_eq!(read, Ok(records.clone()), "{format} round trip failed");
+        }
+    }
+}
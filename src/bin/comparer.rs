@@ -1,11 +1,10 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::BufReader,
-};
-
 use clap::Parser;
-use ypbank_converter::{FileFormat, Record, error::YpbankError, read_all_records};
+use ypbank_converter::{
+    FileFormat,
+    compare::{Diff, Field, compare_records_ignoring},
+    error::YpbankError,
+    read_records_from_path, validate_all,
+};
 
 #[derive(Parser, Debug)]
 pub struct ParserCli {
@@ -20,68 +19,96 @@ pub struct ParserCli {
 
     #[arg(long, value_name = "FORMAT")]
     pub format2: FileFormat,
+
+    /// Field excluded from comparison, may be repeated
+    #[arg(long, value_name = "FIELD")]
+    pub ignore: Vec<Field>,
+
+    /// Print diff as JSON, requires `json` feature
+    #[arg(long)]
+    pub json: bool,
+
+    /// Validate records of both files before comparing, every invalid record is reported
+    #[arg(long)]
+    pub strict: bool,
 }
 
 fn main() -> Result<(), YpbankError> {
     let args = ParserCli::parse();
 
-    let file1 = File::open(&args.file1).map_err(|e| YpbankError::FileOpenError(e.to_string()))?;
-
-    let file2 = File::open(&args.file2).map_err(|e| YpbankError::FileOpenError(e.to_string()))?;
+    let records1 = read_records_from_path(&args.file1, args.format1)?;
+    let records2 = read_records_from_path(&args.file2, args.format2)?;
 
-    let records1 = records_to_map(read_all_records(&mut BufReader::new(file1), args.format1)?);
-    let records2 = records_to_map(read_all_records(&mut BufReader::new(file2), args.format2)?);
-
-    let keys1 = records1.keys().collect::<HashSet<_>>();
-    let keys2 = records2.keys().collect::<HashSet<_>>();
+    if args.strict {
+        let mut invalid = 0;
+        for (file_no, records) in [(1, &records1), (2, &records2)] {
+            for (index, error) in validate_all(records) {
+                println!(
+                    "Invalid transaction in file {file_no}: record {}, id {}: {error}",
+                    index + 1,
+                    records[index].id
+                );
+                invalid += 1;
+            }
+        }
+        if invalid > 0 {
+            return Err(YpbankError::InvalidRecord(format!(
+                "{invalid} records are invalid"
+            )));
+        }
+    }
 
-    let only_in_1 = keys1.difference(&keys2).collect::<Vec<_>>();
-    let only_in_2 = keys2.difference(&keys1).collect::<Vec<_>>();
-    let in_both = keys1.intersection(&keys2).collect::<Vec<_>>();
+    let diff = compare_records_ignoring(&records1, &records2, &args.ignore);
 
-    let different_values = in_both
-        .into_iter()
-        .filter(|id| records1.get(id) != records2.get(id))
-        .collect::<Vec<_>>();
+    if args.json {
+        println!("{}", to_json(&diff)?);
+        return Ok(());
+    }
 
-    if only_in_1.is_empty() && only_in_2.is_empty() && different_values.is_empty() {
+    if diff.is_empty() {
         println!("Transactions are the same");
     } else {
-        if !only_in_1.is_empty() {
+        if !diff.only_in_first.is_empty() {
             println!(
                 "Transactions only in file 1: {}",
-                only_in_1
-                    .iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
+                join_ids(&diff.only_in_first)
             )
         }
-        if !only_in_2.is_empty() {
+        if !diff.only_in_second.is_empty() {
             println!(
                 "Transactions only in file 2: {}",
-                only_in_2
-                    .iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
+                join_ids(&diff.only_in_second)
             )
         }
-        if !different_values.is_empty() {
+        if !diff.differing.is_empty() {
             println!(
                 "Transactions that differs in file1 and file2: {}",
-                different_values
-                    .iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )
+                join_ids(&diff.differing)
+            );
+            for (id, fields) in &diff.fields {
+                for field in fields {
+                    println!("  {id} {}: {} != {}", field.field, field.left, field.right);
+                }
+            }
         }
     }
 
     Ok(())
 }
 
-fn records_to_map(records: Vec<Record>) -> HashMap<u64, Record> {
-    HashMap::from_iter(records.into_iter().map(|r| (r.id, r)))
+#[cfg(feature = "json")]
+fn to_json(diff: &Diff) -> Result<String, YpbankError> {
+    ypbank_converter::compare::diff_to_json(diff)
+}
+
+#[cfg(not(feature = "json"))]
+fn to_json(_diff: &Diff) -> Result<String, YpbankError> {
+    Err(YpbankError::FeatureDisabled("json"))
+}
+
+fn join_ids(ids: &[u64]) -> String {
+    ids.iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
 }
@@ -1,38 +1,334 @@
 use std::{
-    fs::File,
-    io::{self, BufReader, BufWriter, Write},
-    path::PathBuf,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
-use clap::{Parser, arg};
-use ypbank_converter::{FileFormat, error::YpbankError, read_all_records, write_all_records};
+use clap::Parser;
+use ypbank_converter::{
+    DEFAULT_REDACTION, FileFormat, RecordKind, SortKey, convert, detect_format_buffered,
+    error::YpbankError,
+    filter_range, is_gzip_path, is_zstd_path, matches_type, paginate, read_all_records,
+    read_all_records_validated, redact_descriptions,
+    relative_time::{filter_relative, now_millis, parse_duration},
+    schema_violations, sort_records, write_all_records,
+};
 
 #[derive(Parser, Debug)]
 pub struct ConverterCli {
+    /// Input file, stdin is used if omitted or `-`
     #[arg(long, value_name = "FILE")]
-    pub input: PathBuf,
+    pub input: Option<PathBuf>,
 
+    /// Input format, detected by file extension or content if omitted
     #[arg(long, value_name = "FORMAT")]
-    pub input_format: FileFormat,
+    pub input_format: Option<FileFormat>,
 
-    #[arg(long, value_name = "FORMAT")]
-    pub output_format: FileFormat,
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        required_unless_present = "validate_schema"
+    )]
+    pub output_format: Option<FileFormat>,
+
+    /// Output file, stdout is used if omitted
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Keep only records not older than given duration, e.g. 7d, 24h, 30m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub since: Option<Duration>,
+
+    /// Keep only records older than given duration, e.g. 7d, 24h, 30m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub until: Option<Duration>,
+
+    /// Check records against format constraints and print CSV report of violations instead of converting
+    #[arg(long)]
+    pub validate_schema: bool,
+
+    /// Validate records after reading and fail on first invalid record
+    #[arg(long)]
+    pub validate: bool,
+
+    /// Keep only records with timestamp not before given milliseconds
+    #[arg(long, value_name = "MILLIS")]
+    pub from: Option<u64>,
+
+    /// Keep only records with timestamp not after given milliseconds
+    #[arg(long, value_name = "MILLIS")]
+    pub to: Option<u64>,
+
+    /// Keep only records of given type, may be repeated
+    #[arg(long = "type", value_name = "TYPE")]
+    pub types: Vec<RecordKind>,
+
+    /// Sort records by `id`, `timestamp` or `amount`, input order is kept if omitted
+    #[arg(long, value_name = "KEY")]
+    pub sort: Option<SortKey>,
+
+    /// Sort in descending order
+    #[arg(long, requires = "sort")]
+    pub desc: bool,
+
+    /// Drop given number of records after filtering and sorting
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub skip: usize,
+
+    /// Keep at most given number of records after skipping
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Replace descriptions with `[REDACTED]` before writing
+    #[arg(long)]
+    pub redact: bool,
+
+    /// Compression level of `.zst` output from 1 to 22, zstd default level is used if omitted
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub zstd_level: Option<i32>,
 }
 
 fn main() -> Result<(), YpbankError> {
     let args = ConverterCli::parse();
 
-    let file = File::open(&args.input).map_err(|e| YpbankError::FileOpenError(e.to_string()))?;
+    let input = args.input.as_ref().filter(|path| path.as_os_str() != "-");
 
-    let mut file_reader = BufReader::new(file);
+    let mut file_reader: Box<dyn BufRead> = match input {
+        Some(path) => {
+            let file = File::open(path).map_err(|e| YpbankError::FileOpenError(e.to_string()))?;
+            if is_gzip_path(path) {
+                Box::new(BufReader::new(gzip_decoder(file)?))
+            } else if is_zstd_path(path) {
+                Box::new(BufReader::new(zstd_decoder(file)?))
+            } else {
+                Box::new(BufReader::new(file))
+            }
+        }
+        None => Box::new(BufReader::new(io::stdin().lock())),
+    };
 
-    let stdout_handle = io::stdout().lock();
-    let mut stdout_writer = BufWriter::new(stdout_handle);
+    let input_format = match (args.input_format.clone(), input) {
+        (Some(format), _) => format,
+        (None, Some(path)) => FileFormat::from_path(path)?,
+        (None, None) => {
+            detect_format_buffered(&mut file_reader)?.ok_or(YpbankError::UndetectedFormat)?
+        }
+    };
 
-    let records = read_all_records(&mut file_reader, args.input_format)?;
+    let output: Box<dyn FinishWrite> = match &args.output {
+        // Existing output is replaced only after successful conversion
+        Some(path) => {
+            let file = File::create(partial_path(path))
+                .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+            if is_gzip_path(path) {
+                gzip_encoder(file)?
+            } else if is_zstd_path(path) {
+                // Level 0 selects zstd default level
+                zstd_encoder(file, args.zstd_level.unwrap_or(0))?
+            } else {
+                Box::new(file)
+            }
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = BufWriter::new(output);
 
-    write_all_records(&mut stdout_writer, args.output_format, &records)?;
-    stdout_writer
-        .flush()
+    let result = if args.validate_schema {
+        report_schema_violations(&mut file_reader, input_format, &mut writer)
+    } else {
+        write_records(&args, &mut file_reader, input_format, &mut writer).map(|()| 0)
+    };
+    let result = result.and_then(|violations| finish(writer).map(|()| violations));
+
+    if let Some(path) = &args.output {
+        let partial = partial_path(path);
+        match &result {
+            Ok(_) => {
+                fs::rename(&partial, path).map_err(|e| YpbankError::WriteError(e.to_string()))?
+            }
+            // Failure is already reported, leftover partial file only hides it
+            Err(_) => {
+                let _ = fs::remove_file(&partial);
+            }
+        }
+    }
+
+    match result? {
+        0 => Ok(()),
+        violations => Err(YpbankError::InvalidRecord(format!(
+            "{violations} records violate schema"
+        ))),
+    }
+}
+
+/// Path output is written to before it is moved over `path`, kept in the same directory so rename is atomic
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+/// Output which has to be finished after last record, e.g. to write compression trailer
+trait FinishWrite: Write {
+    /// Flush remaining data and write trailer, errors are returned instead of being lost on drop
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+impl FinishWrite for File {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl FinishWrite for io::StdoutLock<'static> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl FinishWrite for flate2::write::GzEncoder<File> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        flate2::write::GzEncoder::finish(*self).map(drop)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl FinishWrite for zstd::stream::write::Encoder<'static, File> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        zstd::stream::write::Encoder::finish(*self).map(drop)
+    }
+}
+
+/// Flush buffered output and finish it
+fn finish(writer: BufWriter<Box<dyn FinishWrite>>) -> Result<(), YpbankError> {
+    writer
+        .into_inner()
+        .map_err(|e| YpbankError::WriteError(e.error().to_string()))?
+        .finish()
         .map_err(|e| YpbankError::WriteError(e.to_string()))
 }
+
+/// Convert records from reader applying filters given in arguments
+fn write_records<R: Read, W: Write>(
+    args: &ConverterCli,
+    mut file_reader: &mut R,
+    input_format: FileFormat,
+    mut writer: &mut W,
+) -> Result<(), YpbankError> {
+    let output_format = args
+        .output_format
+        .clone()
+        .expect("output format is required unless validating schema");
+
+    let filtered = args.since.is_some()
+        || args.until.is_some()
+        || args.from.is_some()
+        || args.to.is_some()
+        || !args.types.is_empty()
+        || args.sort.is_some()
+        || args.skip > 0
+        || args.limit.is_some()
+        || args.redact;
+    if !filtered && !args.validate {
+        convert(&mut file_reader, input_format, &mut writer, output_format)?;
+    } else {
+        let records = if args.validate {
+            read_all_records_validated(&mut file_reader, input_format)?
+        } else {
+            read_all_records(&mut file_reader, input_format)?
+        };
+        let records = filter_relative(records, now_millis(), args.since, args.until);
+        let mut records = filter_range(records, args.from, args.to)?;
+        if !args.types.is_empty() {
+            records.retain(|record| matches_type(record, &args.types));
+        }
+        if let Some(key) = args.sort {
+            sort_records(&mut records, key, args.desc);
+        }
+        let mut records = paginate(records, args.skip, args.limit);
+        if args.redact {
+            redact_descriptions(&mut records, DEFAULT_REDACTION);
+        }
+        write_all_records(&mut writer, output_format, &records)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_decoder(file: File) -> Result<Box<dyn Read>, YpbankError> {
+    Ok(Box::new(flate2::read::GzDecoder::new(file)))
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_encoder(file: File) -> Result<Box<dyn FinishWrite>, YpbankError> {
+    Ok(Box::new(flate2::write::GzEncoder::new(
+        file,
+        flate2::Compression::default(),
+    )))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_decoder(_file: File) -> Result<Box<dyn Read>, YpbankError> {
+    Err(gzip_disabled())
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_encoder(_file: File) -> Result<Box<dyn FinishWrite>, YpbankError> {
+    Err(gzip_disabled())
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_disabled() -> YpbankError {
+    YpbankError::FeatureDisabled("gzip")
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decoder(file: File) -> Result<Box<dyn Read>, YpbankError> {
+    Ok(Box::new(zstd::stream::read::Decoder::new(file)?))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_encoder(file: File, level: i32) -> Result<Box<dyn FinishWrite>, YpbankError> {
+    Ok(Box::new(zstd::stream::write::Encoder::new(file, level)?))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decoder(_file: File) -> Result<Box<dyn Read>, YpbankError> {
+    Err(zstd_disabled())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_encoder(_file: File, _level: i32) -> Result<Box<dyn FinishWrite>, YpbankError> {
+    Err(zstd_disabled())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_disabled() -> YpbankError {
+    YpbankError::FeatureDisabled("zstd")
+}
+
+/// Write CSV report of schema violations, returns number of violations
+fn report_schema_violations<R: Read, W: Write>(
+    reader: &mut R,
+    input_format: FileFormat,
+    writer: &mut W,
+) -> Result<usize, YpbankError> {
+    let violations = schema_violations(reader, input_format)?;
+
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer
+        .write_record(["ROW", "ERROR"])
+        .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+    for (row, error) in &violations {
+        csv_writer
+            .write_record([row.to_string(), error.to_string()])
+            .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+    }
+    csv_writer
+        .flush()
+        .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+
+    Ok(violations.len())
+}
@@ -0,0 +1,44 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use ypbank_converter::{
+    FileFormat, error::YpbankError, generate::generate_records, write_all_records,
+};
+
+#[derive(Parser, Debug)]
+pub struct GenerateCli {
+    /// Number of generated records
+    #[arg(long)]
+    pub count: usize,
+
+    /// Seed of generator, the same seed gives the same records
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    #[arg(long, value_name = "FORMAT")]
+    pub format: FileFormat,
+
+    /// Output file, stdout is used if omitted
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+fn main() -> Result<(), YpbankError> {
+    let args = GenerateCli::parse();
+
+    let records = generate_records(args.count, args.seed);
+
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| YpbankError::WriteError(e.to_string()))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    write_all_records(&mut writer, args.format, &records)?;
+    Ok(writer.flush()?)
+}
@@ -0,0 +1,111 @@
+use std::{fs::File, io::BufReader, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use ypbank_converter::{
+    Record,
+    bin_format::{BinRecordReader, RawFrame, read_one_raw},
+    error::YpbankError,
+};
+
+#[derive(Parser, Debug)]
+pub struct InspectCli {
+    #[arg(long, value_name = "FILE")]
+    pub input: PathBuf,
+
+    /// Records are followed by CRC32 checksum
+    #[arg(long)]
+    pub checksums: bool,
+
+    /// Fixed width fields are little-endian
+    #[arg(long)]
+    pub little_endian: bool,
+}
+
+/// Number of bytes in one line of hex dump
+const BYTES_PER_LINE: usize = 16;
+
+fn main() -> Result<ExitCode, YpbankError> {
+    let args = InspectCli::parse();
+
+    let file = File::open(&args.input).map_err(|e| YpbankError::FileOpenError(e.to_string()))?;
+    let mut reader = BufReader::new(file);
+    let record_reader =
+        BinRecordReader::with_checksums(args.checksums).little_endian(args.little_endian);
+    let mut offset = 0;
+
+    loop {
+        let frame = match read_one_raw(&mut reader) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(ExitCode::SUCCESS),
+            Err(e) => {
+                eprintln!("Error at offset {offset:#010x}: {e}");
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+
+        print_frame(offset, &frame);
+        match frame.decode_with(&record_reader) {
+            Ok(Some(record)) => print_record(&record),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("Error at offset {offset:#010x}: {e}");
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        print_hex_dump(offset, &frame.bytes);
+        println!();
+
+        offset += frame.bytes.len();
+    }
+}
+
+fn print_frame(offset: usize, frame: &RawFrame) {
+    println!("Offset: {offset:#010x}");
+    println!("Magic: {}", String::from_utf8_lossy(&frame.magic));
+    println!("Version: {}", frame.version);
+    if frame.is_count_header() {
+        println!("Record count: {}", frame.declared_length);
+    } else {
+        println!("Declared length: {}", frame.declared_length);
+    }
+}
+
+fn print_record(record: &Record) {
+    println!("  TX_ID: {}", record.id);
+    println!("  TX_TYPE: {}", record.record_type());
+    println!("  AMOUNT: {}", record.amount());
+    println!("  CURRENCY: {}", record.currency());
+    println!("  FEE: {}", record.fee());
+    println!("  REFERENCE: {:?}", record.reference());
+    println!("  TIMESTAMP: {}", record.timestamp());
+    println!("  STATUS: {}", record.status());
+    println!(
+        "  DESCRIPTION: {:?}",
+        record.description().unwrap_or_default()
+    );
+}
+
+fn print_hex_dump(offset: usize, bytes: &[u8]) {
+    for (line, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let hex = chunk
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        println!(
+            "{:08x}  {hex:<width$}  |{ascii}|",
+            offset + line * BYTES_PER_LINE,
+            width = BYTES_PER_LINE * 3 - 1
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use std::{
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use clap::Parser;
+use ypbank_converter::{
+    FileFormat, error::YpbankError, merge_records, read_records_from_path, write_all_records,
+};
+
+#[derive(Parser, Debug)]
+pub struct MergeCli {
+    /// Input file with its format, e.g. `records.csv:csv`, may be repeated
+    #[arg(long, value_name = "FILE:FORMAT", value_parser = parse_input)]
+    pub input: Vec<(PathBuf, FileFormat)>,
+
+    #[arg(long, value_name = "FORMAT")]
+    pub output_format: FileFormat,
+
+    /// Collapse identical records with the same id, fail on conflicting ones
+    #[arg(long)]
+    pub dedup: bool,
+}
+
+fn parse_input(s: &str) -> Result<(PathBuf, FileFormat), YpbankError> {
+    let (path, format) = s
+        .rsplit_once(':')
+        .ok_or_else(|| YpbankError::InvalidArgument(format!("'{s}', expected FILE:FORMAT")))?;
+    Ok((PathBuf::from(path), FileFormat::from_str(format)?))
+}
+
+fn main() -> Result<(), YpbankError> {
+    let args = MergeCli::parse();
+
+    let sources = args
+        .input
+        .iter()
+        .map(|(path, format)| read_records_from_path(path, format.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let records = merge_records(sources, args.dedup)?;
+
+    let mut writer = BufWriter::new(io::stdout().lock());
+    write_all_records(&mut writer, args.output_format, &records)?;
+    Ok(writer.flush()?)
+}
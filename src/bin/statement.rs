@@ -0,0 +1,148 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::{Parser, ValueEnum};
+use ypbank_converter::{
+    FileFormat, Record, RecordStatus, error::YpbankError, read_records_from_path,
+};
+
+#[derive(Parser, Debug)]
+pub struct StatementCli {
+    #[arg(long, value_name = "ID")]
+    pub user: u64,
+
+    #[arg(long, value_name = "FILE")]
+    pub input: PathBuf,
+
+    #[arg(long, value_name = "FORMAT")]
+    pub input_format: FileFormat,
+
+    #[arg(long, value_name = "STATEMENT_FORMAT", default_value = "text")]
+    pub statement_format: StatementFormat,
+
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+pub enum StatementFormat {
+    Text,
+    Csv,
+}
+
+struct StatementLine<'a> {
+    record: &'a Record,
+    change: i128,
+    balance: i128,
+}
+
+fn main() -> Result<(), YpbankError> {
+    let args = StatementCli::parse();
+
+    let mut records = read_records_from_path(&args.input, args.input_format)?;
+    records.retain(|r| r.record_type().user_ids().contains(&args.user));
+    records.sort_by_key(|r| r.timestamp());
+
+    let mut balance = 0i128;
+    let lines = records
+        .iter()
+        .map(|record| {
+            let change = balance_change(record, args.user);
+            balance += change;
+            StatementLine {
+                record,
+                change,
+                balance,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| YpbankError::FileOpenError(e.to_string()))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    match args.statement_format {
+        StatementFormat::Text => write_text(&mut writer, args.user, &lines, balance)?,
+        StatementFormat::Csv => write_csv(&mut writer, &lines)?,
+    }
+
+    Ok(writer.flush()?)
+}
+
+/// Signed change of user balance caused by record, only successful records are counted and fee is charged to primary user of record
+fn balance_change(record: &Record, user: u64) -> i128 {
+    if *record.status() != RecordStatus::Success {
+        return 0;
+    }
+
+    record
+        .balance_changes()
+        .into_iter()
+        .filter(|(user_id, _)| *user_id == user)
+        .map(|(_, change)| change)
+        .sum()
+}
+
+fn write_text<W: Write>(
+    w: &mut W,
+    user: u64,
+    lines: &[StatementLine],
+    closing_balance: i128,
+) -> Result<(), YpbankError> {
+    writeln!(w, "Statement for user {user}")?;
+    for line in lines {
+        writeln!(
+            w,
+            "{} {} {} {} {:+} balance {} \"{}\"",
+            line.record.timestamp(),
+            line.record.id,
+            line.record.record_type(),
+            line.record.status(),
+            line.change,
+            line.balance,
+            line.record.description().unwrap_or_default()
+        )?;
+    }
+    writeln!(w, "Closing balance: {closing_balance}")?;
+    Ok(())
+}
+
+fn write_csv<W: Write>(w: &mut W, lines: &[StatementLine]) -> Result<(), YpbankError> {
+    let mut writer = csv::Writer::from_writer(w);
+
+    writer
+        .write_record([
+            "TIMESTAMP",
+            "TX_ID",
+            "TX_TYPE",
+            "STATUS",
+            "CHANGE",
+            "BALANCE",
+            "DESCRIPTION",
+        ])
+        .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+
+    for line in lines {
+        writer
+            .write_record([
+                line.record.timestamp().to_string(),
+                line.record.id.to_string(),
+                line.record.record_type().to_string(),
+                line.record.status().to_string(),
+                line.change.to_string(),
+                line.balance.to_string(),
+                line.record.description().unwrap_or_default().to_string(),
+            ])
+            .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+    }
+
+    writer
+        .flush()
+        .map_err(|e| YpbankError::WriteError(e.to_string()))
+}
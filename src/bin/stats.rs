@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use ypbank_converter::{
+    FileFormat,
+    error::YpbankError,
+    read_records_from_path,
+    stats::{group_by_user, summarize},
+};
+
+#[derive(Parser, Debug)]
+pub struct StatsCli {
+    #[arg(long, value_name = "FILE")]
+    pub input: PathBuf,
+
+    #[arg(long, value_name = "FORMAT")]
+    pub format: FileFormat,
+}
+
+fn main() -> Result<(), YpbankError> {
+    let args = StatsCli::parse();
+
+    let records = read_records_from_path(&args.input, args.format)?;
+    let summary = summarize(&records);
+
+    println!("Records: {}", summary.count);
+    println!("Types:");
+    for (kind, count) in &summary.by_kind {
+        println!("  {kind}: {count}");
+    }
+    println!("Statuses:");
+    for (status, count) in &summary.by_status {
+        println!("  {status}: {count}");
+    }
+    println!("Users:");
+    for (user, records) in group_by_user(&records) {
+        println!("  {user}: {}", records.len());
+    }
+    println!("Total amount: {}", summary.total_amount);
+    if let Some(average) = summary.average_amount() {
+        println!("Average amount: {average:.2}");
+    }
+    if let (Some(min), Some(max)) = (summary.min_timestamp, summary.max_timestamp) {
+        println!("First timestamp: {min}");
+        println!("Last timestamp: {max}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use ypbank_converter::{FileFormat, error::YpbankError, read_records_from_path, validate_all};
+
+#[derive(Parser, Debug)]
+pub struct ValidatorCli {
+    #[arg(long, value_name = "FILE")]
+    pub input: PathBuf,
+
+    #[arg(long, value_name = "FORMAT")]
+    pub format: FileFormat,
+}
+
+fn main() -> Result<ExitCode, YpbankError> {
+    let args = ValidatorCli::parse();
+
+    let records = read_records_from_path(&args.input, args.format)?;
+    let failures = validate_all(&records);
+
+    for (index, error) in &failures {
+        println!("record {}, id {}: {error}", index + 1, records[*index].id);
+    }
+
+    if failures.is_empty() {
+        println!("All {} records are valid", records.len());
+        Ok(ExitCode::SUCCESS)
+    } else {
+        eprintln!(
+            "{} of {} records are invalid",
+            failures.len(),
+            records.len()
+        );
+        Ok(ExitCode::FAILURE)
+    }
+}
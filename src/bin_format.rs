@@ -1,40 +1,342 @@
-use std::io::{self, Read, Write};
+//! Module with reader and writer of YPBankBin format
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Seek, SeekFrom, Write},
+};
 
-use crate::{Record, RecordReader, RecordStatus, RecordType, RecordWriter, error::YpbankError};
+use crate::{
+    ReaderConfig, Record, RecordKind, RecordReader, RecordStatus, RecordType, RecordWriter,
+    error::{ErrorSource, Result, YpbankError},
+};
 
-pub(crate) struct BinRecordReader;
+/// Layout of records in binary stream
+#[derive(Debug, Clone, Copy)]
+enum Framing {
+    /// Every record starts with magic header and record length
+    Headered,
+    /// Records follow each other without header and length, descriptions are zero padded to fixed length
+    Headerless { description_len: usize },
+    /// Every record starts with varint magic header and record length, integer fields are LEB128 encoded
+    Varint,
+}
+
+/// Encoding of integer record fields, detected by record header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    /// Big endian integers of fixed width
+    Fixed,
+    /// LEB128 encoded integers
+    Varint,
+}
+
+/// Reader of binary records
+#[derive(Clone)]
+pub struct BinRecordReader {
+    framing: Framing,
+    max_description_len: usize,
+    /// Reject deposits with sender and withdrawals or fees with receiver
+    reject_unused_user_ids: bool,
+    checksums: bool,
+    /// Treat bytes after last complete record as error, otherwise they are ignored
+    strict: bool,
+    /// Replace invalid UTF-8 in descriptions instead of failing
+    lossy: bool,
+    /// Fixed width integer fields are little-endian
+    little_endian: bool,
+}
 
 impl BinRecordReader {
-    pub(crate) fn new() -> Self {
-        Self
+    /// Default limit for declared description length, protects from allocating huge buffers
+    const DEFAULT_MAX_DESCRIPTION_LEN: usize = 16 * 1024 * 1024;
+
+    /// Limit for records preallocated by count header, protects from allocating huge buffers
+    const MAX_PREALLOCATED: u64 = 1024 * 1024;
+
+    /// Reader of records with header and length.
+    ///
+    /// Unlike default [`ReaderConfig`], trailing bytes are an error, pass non-strict config to [`BinRecordReader::config`] to ignore them
+    pub fn new() -> Self {
+        Self {
+            framing: Framing::Headered,
+            max_description_len: Self::DEFAULT_MAX_DESCRIPTION_LEN,
+            reject_unused_user_ids: false,
+            checksums: false,
+            strict: true,
+            lossy: false,
+            little_endian: false,
+        }
     }
 
-    fn has_next_record(&self, r: &mut dyn std::io::Read) -> Result<bool, YpbankError> {
-        let mut buffer = vec![0u8; BinRecord::HEADER.len()];
-        let mut bytes_read = 0;
+    /// Reader rejecting records declaring description longer than `max_description_len` bytes
+    pub fn with_max_description_len(max_description_len: usize) -> Self {
+        Self::new().max_description_len(max_description_len)
+    }
 
-        while bytes_read < buffer.len() {
-            let bytes_to_fill = &mut buffer[bytes_read..];
+    /// Reader ignoring trailing bytes unless `config` is strict
+    pub fn with_config(config: ReaderConfig) -> Self {
+        Self::new().config(config)
+    }
 
-            match r.read(bytes_to_fill) {
-                Ok(0) if bytes_read > 0 => {
-                    return Err(YpbankError::BinaryUnexpectedValue);
-                }
-                Ok(0) => return Ok(false),
-                Ok(n) => {
-                    bytes_read += n;
-                }
-                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
-                Err(e) => return Err(YpbankError::BinaryReadError(e.to_string())),
+    /// Reader expecting CRC32 after every record if `checksums` is set
+    pub fn with_checksums(checksums: bool) -> Self {
+        Self::new().checksums(checksums)
+    }
+
+    /// Reject records declaring description longer than `max_description_len` bytes
+    pub fn max_description_len(mut self, max_description_len: usize) -> Self {
+        self.max_description_len = max_description_len;
+        self
+    }
+
+    /// Ignore trailing bytes unless `config` is strict, reject unused user ids if `config` says so
+    pub fn config(mut self, config: ReaderConfig) -> Self {
+        self.strict = config.strict;
+        self.reject_unused_user_ids = config.reject_unused_user_ids;
+        self
+    }
+
+    /// Expect CRC32 after every record if `checksums` is set
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Decode descriptions with U+FFFD in place of invalid UTF-8 if `lossy` is set
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    /// Read fixed width integer fields and description length in little-endian byte order if `little_endian` is set
+    pub fn little_endian(mut self, little_endian: bool) -> Self {
+        self.little_endian = little_endian;
+        self
+    }
+
+    /// Read records without header and length, descriptions are zero padded to `description_len` bytes
+    pub fn headerless(mut self, description_len: usize) -> Self {
+        self.framing = Framing::Headerless { description_len };
+        self
+    }
+
+    /// Read header of next record, returns None if reader has no data left.
+    ///
+    /// Bytes not forming header are trailing data only after at least one record and if no record header follows them,
+    /// otherwise stream is corrupted
+    fn has_next_record(&self, r: &mut dyn Read, after_record: bool) -> Result<Option<Encoding>> {
+        let mut buffer = [0u8; BinRecord::HEADER.len()];
+
+        let header_follows = match fill_buffer(r, &mut buffer) {
+            Ok(false) => return Ok(None),
+            Ok(true) if buffer == *BinRecord::HEADER => return Ok(Some(Encoding::Fixed)),
+            Ok(true) if buffer == *BinRecord::VARINT_HEADER => return Ok(Some(Encoding::Varint)),
+            Ok(true) => contains_header(&mut (&buffer[1..]).chain(r))?,
+            // Incomplete header, input has ended
+            Err(YpbankError::BinaryTrailingData) => false,
+            Err(e) => return Err(e),
+        };
+
+        if !after_record || header_follows {
+            Err(YpbankError::BinaryUnexpectedValue)
+        } else if self.strict {
+            Err(YpbankError::BinaryTrailingData)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Fill whole buffer, incomplete trailing data counts as end of input unless reader is strict
+    fn fill_next(&self, r: &mut dyn Read, buffer: &mut [u8]) -> Result<bool> {
+        match fill_buffer(r, buffer) {
+            Err(YpbankError::BinaryTrailingData) if !self.strict => Ok(false),
+            result => result,
+        }
+    }
+}
+
+/// Map id of every record to byte offset of its header, the first offset is kept for repeated ids.
+///
+/// Offsets can be passed to [`read_record_at`] to read single record without scanning the file
+pub fn build_index<R: Read + Seek>(r: &mut R) -> Result<BTreeMap<u64, u64>> {
+    let reader = BinRecordReader::new();
+    let mut index = BTreeMap::new();
+    let mut after_record = false;
+
+    loop {
+        let offset = r
+            .stream_position()
+            .map_err(|e| YpbankError::BinaryReadError(ErrorSource::new(e)))?;
+        match reader.read_next_frame(r, after_record)? {
+            Some(Frame::Record(record)) => {
+                index.entry(u64::from_be_bytes(record.id)).or_insert(offset);
+                after_record = true;
             }
+            Some(Frame::Count(_)) => continue,
+            None => return Ok(index),
         }
+    }
+}
 
-        if buffer == BinRecord::HEADER {
-            Ok(true)
-        } else {
-            Err(YpbankError::BinaryUnexpectedValue)
+/// Read single record starting at given byte offset, e.g. taken from [`build_index`]
+pub fn read_record_at<R: Read + Seek>(r: &mut R, offset: u64) -> Result<Record> {
+    r.seek(SeekFrom::Start(offset))
+        .map_err(|e| YpbankError::BinaryReadError(ErrorSource::new(e)))?;
+    BinRecordReader::new().read_next(r, false)?.ok_or_else(|| {
+        YpbankError::BinaryReadError(ErrorSource::new(io::Error::from(
+            io::ErrorKind::UnexpectedEof,
+        )))
+    })
+}
+
+/// Check if rest of input contains header of fixed width or varint record
+fn contains_header(r: &mut dyn Read) -> Result<bool> {
+    let mut window = vec![];
+    let mut chunk = [0u8; 8 * 1024];
+
+    loop {
+        let n = match r.read(&mut chunk) {
+            Ok(0) => return Ok(false),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(YpbankError::BinaryReadError(ErrorSource::new(e))),
+        };
+        window.extend_from_slice(&chunk[..n]);
+        if window
+            .windows(BinRecord::HEADER.len())
+            .any(starts_with_header)
+        {
+            return Ok(true);
+        }
+        // Keep bytes which can start header split between chunks
+        window.drain(..window.len().saturating_sub(BinRecord::HEADER.len() - 1));
+    }
+}
+
+/// Check if bytes start with header of fixed width or varint record
+pub(crate) fn starts_with_header(bytes: &[u8]) -> bool {
+    bytes.starts_with(BinRecord::HEADER) || bytes.starts_with(BinRecord::VARINT_HEADER)
+}
+
+/// Reader computing CRC32 of all bytes read through it
+struct ChecksumReader<'a> {
+    inner: &'a mut dyn Read,
+    hasher: crc32fast::Hasher,
+}
+
+impl<'a> ChecksumReader<'a> {
+    fn new(inner: &'a mut dyn Read) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+}
+
+impl Read for ChecksumReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Reader keeping copy of all bytes read through it
+struct RecordingReader<'a> {
+    inner: &'a mut dyn Read,
+    bytes: Vec<u8>,
+}
+
+impl Read for RecordingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Undecoded frame of headered binary stream, see [`read_one_raw`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame {
+    /// Frame header, `YPBN` or `YPBV`
+    pub magic: [u8; 4],
+    /// Format version byte, 0 for legacy records
+    pub version: u8,
+    /// Declared record length, or number of records for file header
+    pub declared_length: u64,
+    /// All bytes of the frame including header
+    pub bytes: Vec<u8>,
+}
+
+impl RawFrame {
+    /// Check if frame is file header with number of records instead of record
+    pub fn is_count_header(&self) -> bool {
+        self.version == BinRecord::COUNT_HEADER_VERSION
+    }
+
+    /// Decode record held by frame, `None` for file header
+    pub fn decode(&self) -> Result<Option<Record>> {
+        self.decode_with(&BinRecordReader::new())
+    }
+
+    /// Decode record held by frame with options of given reader, e.g. checksums or little-endian fields
+    pub fn decode_with(&self, reader: &BinRecordReader) -> Result<Option<Record>> {
+        reader.read_next(&mut &self.bytes[..], false)
+    }
+}
+
+/// Read next frame of headered binary stream without decoding record fields, returns None if reader has no data left
+pub fn read_one_raw<R: Read>(r: &mut R) -> Result<Option<RawFrame>> {
+    let mut magic = [0u8; BinRecord::HEADER.len()];
+    if !fill_buffer(r, &mut magic)? {
+        return Ok(None);
+    }
+    if !starts_with_header(&magic) {
+        return Err(YpbankError::BinaryUnexpectedValue);
+    }
+
+    let mut recorder = RecordingReader {
+        inner: r,
+        bytes: magic.to_vec(),
+    };
+    let declared_length = match read_frame_length(&mut recorder)? {
+        FrameLength::Record { length, .. } => {
+            read_bytes(&mut recorder, length as usize)?;
+            u64::from(length)
+        }
+        FrameLength::Count(count) => count,
+    };
+
+    Ok(Some(RawFrame {
+        magic,
+        version: recorder.bytes[magic.len()],
+        declared_length,
+        bytes: recorder.bytes,
+    }))
+}
+
+/// Fill whole buffer from reader, returns false if reader has no data left.
+///
+/// Buffer is filled at record boundary, so reader ending in the middle of it has trailing data
+fn fill_buffer(r: &mut dyn std::io::Read, buffer: &mut [u8]) -> Result<bool> {
+    let mut bytes_read = 0;
+
+    while bytes_read < buffer.len() {
+        let bytes_to_fill = &mut buffer[bytes_read..];
+
+        match r.read(bytes_to_fill) {
+            Ok(0) if bytes_read > 0 => {
+                return Err(YpbankError::BinaryTrailingData);
+            }
+            Ok(0) => return Ok(false),
+            Ok(n) => {
+                bytes_read += n;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(YpbankError::BinaryReadError(ErrorSource::new(e))),
         }
     }
+
+    Ok(true)
 }
 
 macro_rules! read_n_bytes {
@@ -43,99 +345,433 @@ macro_rules! read_n_bytes {
 
         match $reader.read_exact(&mut buffer) {
             Ok(_) => Ok(buffer),
-            Err(e) => Err($crate::error::YpbankError::BinaryReadError(e.to_string())),
+            Err(e) => Err($crate::error::YpbankError::BinaryReadError(
+                $crate::error::ErrorSource::new(e),
+            )),
         }
     }};
 }
 
+impl Default for BinRecordReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RecordReader for BinRecordReader {
-    fn read_all<R: Read>(&self, r: &mut R) -> Result<Vec<Record>, YpbankError> {
-        let mut bin_records: Vec<BinRecord> = vec![];
-        loop {
-            if !self.has_next_record(r)? {
-                break;
+    fn read_all<R: Read>(&self, r: &mut R) -> Result<Vec<Record>> {
+        let mut records = vec![];
+        while let Some(frame) = self.read_next_frame(r, !records.is_empty())? {
+            match frame {
+                Frame::Count(count) => records.reserve(count.min(Self::MAX_PREALLOCATED) as usize),
+                Frame::Record(bin_record) => records.push(self.decode(bin_record)?),
             }
+        }
+        Ok(records)
+    }
 
-            let mut record_bytes_left = u32::from_be_bytes(read_n_bytes!(r, 4)?);
+    fn records<'a>(&self, r: &'a mut dyn Read) -> Box<dyn Iterator<Item = Result<Record>> + 'a> {
+        let reader = self.clone();
+        let mut failed = false;
+        let mut after_record = false;
 
-            let fixed_part_length = 8 + 1 + 8 + 8 + 8 + 8 + 1 + 4;
+        Box::new(std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let next = reader.read_next(r, after_record).transpose();
+            failed = matches!(next, Some(Err(_)));
+            after_record |= matches!(next, Some(Ok(_)));
+            next
+        }))
+    }
+}
 
-            if record_bytes_left < fixed_part_length {
-                return Err(YpbankError::BinaryRecordTooShort);
+impl BinRecordReader {
+    /// Number of checksum bytes following every record
+    fn checksum_length(&self) -> u32 {
+        if self.checksums { 4 } else { 0 }
+    }
+
+    /// Read CRC32 following record and compare it with hash of record body
+    fn verify_checksum(&self, r: &mut dyn Read, id: [u8; 8], hash: u32) -> Result<()> {
+        if !self.checksums {
+            return Ok(());
+        }
+
+        let checksum = u32::from_be_bytes(read_n_bytes!(r, 4)?);
+        if checksum != hash {
+            return Err(YpbankError::BinaryChecksumMismatch {
+                id: u64::from_be_bytes(id),
+            });
+        }
+        Ok(())
+    }
+
+    /// Read next record, returns None if reader has no data left.
+    ///
+    /// `after_record` tells if at least one record was read before, only then unknown bytes can be trailing data
+    fn read_next(&self, r: &mut dyn Read, after_record: bool) -> Result<Option<Record>> {
+        while let Some(frame) = self.read_next_frame(r, after_record)? {
+            if let Frame::Record(bin_record) = frame {
+                return self.decode(bin_record).map(Some);
             }
+        }
+        Ok(None)
+    }
 
-            let id = read_n_bytes!(r, 8)?;
-            let record_type = read_n_bytes!(r, 1)?[0];
-            let from_user_id = read_n_bytes!(r, 8)?;
-            let to_user_id = read_n_bytes!(r, 8)?;
-            let amount = read_n_bytes!(r, 8)?;
-            let timestamp = read_n_bytes!(r, 8)?;
-            let status = read_n_bytes!(r, 1)?[0];
-            let description_length = u32::from_be_bytes(read_n_bytes!(r, 4)?);
-
-            record_bytes_left -= fixed_part_length;
-            if record_bytes_left < description_length {
-                return Err(YpbankError::BinaryDescriptionTooLong);
+    /// Convert binary record to record, replacing invalid UTF-8 if reader is lossy
+    fn decode(&self, mut bin_record: BinRecord) -> Result<Record> {
+        if self.lossy {
+            for text in [&mut bin_record.description, &mut bin_record.reference] {
+                *text = String::from_utf8_lossy(text).into_owned().into_bytes();
             }
+        }
+        bin_record.into_record(self.reject_unused_user_ids)
+    }
+
+    /// Read next record or record count header, returns None if reader has no data left.
+    ///
+    /// `after_record` tells if at least one record was read before, only then unknown bytes can be trailing data
+    fn read_next_frame(&self, r: &mut dyn Read, after_record: bool) -> Result<Option<Frame>> {
+        if let Framing::Headerless { description_len } = self.framing {
+            return Ok(self
+                .read_next_headerless(r, description_len)?
+                .map(Frame::Record));
+        }
 
-            let mut description = vec![0u8; description_length as usize];
-            if let Err(e) = r.read_exact(&mut description) {
-                return Err(YpbankError::BinaryReadError(e.to_string()));
+        let Some(encoding) = self.has_next_record(r, after_record)? else {
+            return Ok(None);
+        };
+        match read_frame_length(r)? {
+            FrameLength::Record { length, version } => self
+                .read_headered_body(r, encoding, length, version)
+                .map(|record| Some(Frame::Record(record))),
+            FrameLength::Count(count) if encoding == Encoding::Fixed => {
+                Ok(Some(Frame::Count(count)))
             }
+            FrameLength::Count(_) => Err(YpbankError::UnsupportedBinaryVersion(
+                BinRecord::COUNT_HEADER_VERSION,
+            )),
+        }
+    }
 
-            record_bytes_left -= description_length;
-            if record_bytes_left > 0 {
-                let mut skip_bytes = vec![0u8; record_bytes_left as usize];
-                if let Err(e) = r.read_exact(&mut skip_bytes) {
-                    return Err(YpbankError::BinaryReadError(e.to_string()));
-                }
+    fn read_headered_body(
+        &self,
+        r: &mut dyn Read,
+        encoding: Encoding,
+        record_length: u32,
+        version: u8,
+    ) -> Result<BinRecord> {
+        let checksum_length = self.checksum_length();
+        let trailer_length = BinRecord::trailer_length(version);
+
+        if encoding == Encoding::Varint {
+            let mut body = read_bytes(r, record_length as usize)?;
+            let checksum = body.split_off(
+                body.len()
+                    .checked_sub(checksum_length as usize)
+                    .ok_or(YpbankError::BinaryRecordTooShort)?,
+            );
+            let record = BinRecord::decode_varint(&body, self.max_description_len, version)?;
+            self.verify_checksum(&mut &checksum[..], record.id, crc32fast::hash(&body))?;
+            return Ok(record);
+        }
+
+        let r = &mut ChecksumReader::new(r);
+
+        if record_length < BinRecord::FIXED_PART_LENGTH {
+            return Err(YpbankError::BinaryRecordTooShort);
+        }
+
+        let id = read_n_bytes!(r, 8)?;
+        let record_type = read_n_bytes!(r, 1)?[0];
+        let from_user_id = read_n_bytes!(r, 8)?;
+        let to_user_id = read_n_bytes!(r, 8)?;
+        let amount = read_n_bytes!(r, 8)?;
+        let timestamp = read_n_bytes!(r, 8)?;
+        let status = read_n_bytes!(r, 1)?[0];
+        let original_tx_id = if record_type == BinRecord::REVERSAL_TYPE {
+            Some(read_n_bytes!(r, 8)?)
+        } else {
+            None
+        };
+        let description_length = self.read_length(r)?;
+
+        let fixed_part_length =
+            BinRecord::FIXED_PART_LENGTH + BinRecord::extension_length(record_type);
+        if record_length < fixed_part_length {
+            return Err(YpbankError::BinaryRecordTooShort);
+        }
+        if description_length as usize > self.max_description_len {
+            return Err(YpbankError::BinaryDescriptionTooLong);
+        }
+        // Bytes left for reference, checksum is not included
+        let mut record_bytes_left = (record_length - fixed_part_length)
+            .checked_sub(checksum_length + trailer_length)
+            .ok_or(YpbankError::BinaryRecordTooShort)?
+            .checked_sub(description_length)
+            .ok_or(YpbankError::BinaryDescriptionTooLong)?;
+
+        let description = read_bytes(r, description_length as usize)?;
+        let currency = if BinRecord::has_currency(version) {
+            read_n_bytes!(r, 3)?
+        } else {
+            *BinRecord::DEFAULT_CURRENCY
+        };
+        let fee = if BinRecord::has_fee(version) {
+            read_n_bytes!(r, 8)?
+        } else {
+            [0; 8]
+        };
+
+        let reference = if version == BinRecord::VERSION {
+            let reference_length = self.read_length(r)?;
+            if record_bytes_left < reference_length
+                || reference_length as usize > self.max_description_len
+            {
+                return Err(YpbankError::BinaryDescriptionTooLong);
             }
+            record_bytes_left -= reference_length;
+            read_bytes(r, reference_length as usize)?
+        } else {
+            vec![]
+        };
+        if record_bytes_left != 0 {
+            return Err(YpbankError::BinaryLengthMismatch(
+                record_length,
+                record_length - record_bytes_left,
+            ));
+        }
+        let hash = r.hasher.clone().finalize();
 
-            bin_records.push(BinRecord {
-                id,
-                record_type,
-                from_user_id,
-                to_user_id,
-                amount,
-                timestamp,
-                status,
-                description,
-            });
+        let record = self.to_big_endian(BinRecord {
+            id,
+            record_type,
+            from_user_id,
+            to_user_id,
+            amount,
+            timestamp,
+            status,
+            original_tx_id,
+            description,
+            currency,
+            fee,
+            reference,
+        });
+        self.verify_checksum(r.inner, record.id, hash)?;
+
+        Ok(record)
+    }
+
+    /// Read length of description or reference in byte order of reader
+    fn read_length(&self, r: &mut dyn Read) -> Result<u32> {
+        let length = read_n_bytes!(r, 4)?;
+        Ok(if self.little_endian {
+            u32::from_le_bytes(length)
+        } else {
+            u32::from_be_bytes(length)
+        })
+    }
+
+    /// Convert fields read in little-endian byte order to big-endian representation of [`BinRecord`]
+    fn to_big_endian(&self, mut record: BinRecord) -> BinRecord {
+        if self.little_endian {
+            record.swap_byte_order();
+        }
+        record
+    }
+
+    /// Count records skipping their bodies, only header and length of every record are decoded.
+    ///
+    /// Count written in file header is returned without reading records
+    pub fn count<R: Read>(&self, r: &mut R) -> Result<usize> {
+        let Framing::Headered = self.framing else {
+            return self.read_all(r).map(|records| records.len());
+        };
+
+        let mut count = 0;
+        while self.has_next_record(r, count > 0)?.is_some() {
+            let record_length = match read_frame_length(r)? {
+                FrameLength::Record { length, .. } => u64::from(length),
+                FrameLength::Count(header_count) if count == 0 => return Ok(header_count as usize),
+                FrameLength::Count(_) => continue,
+            };
+            let skipped = io::copy(&mut r.by_ref().take(record_length), &mut io::sink())
+                .map_err(|e| YpbankError::BinaryReadError(ErrorSource::new(e)))?;
+            if skipped < record_length {
+                return Err(YpbankError::BinaryRecordTooShort);
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn read_next_headerless(
+        &self,
+        r: &mut dyn Read,
+        description_len: usize,
+    ) -> Result<Option<BinRecord>> {
+        let mut frame = vec![0u8; BinRecord::FIELDS_LENGTH as usize + description_len];
+
+        if !self.fill_next(r, &mut frame)? {
+            return Ok(None);
         }
 
-        bin_records.into_iter().map(|br| br.try_into()).collect()
+        // Frame of reversal is longer by original transaction id placed before description
+        let record_type = frame[8];
+        frame.extend(read_bytes(
+            r,
+            BinRecord::extension_length(record_type) as usize,
+        )?);
+
+        let mut fields = &frame[..];
+
+        let id = read_n_bytes!(fields, 8)?;
+        let record_type = read_n_bytes!(fields, 1)?[0];
+        let from_user_id = read_n_bytes!(fields, 8)?;
+        let to_user_id = read_n_bytes!(fields, 8)?;
+        let amount = read_n_bytes!(fields, 8)?;
+        let timestamp = read_n_bytes!(fields, 8)?;
+        let status = read_n_bytes!(fields, 1)?[0];
+        let original_tx_id = if record_type == BinRecord::REVERSAL_TYPE {
+            Some(read_n_bytes!(fields, 8)?)
+        } else {
+            None
+        };
+
+        let padding_start = fields.iter().rposition(|b| *b != 0).map_or(0, |p| p + 1);
+
+        let hash = crc32fast::hash(&frame);
+
+        let record = self.to_big_endian(BinRecord {
+            id,
+            record_type,
+            from_user_id,
+            to_user_id,
+            amount,
+            timestamp,
+            status,
+            original_tx_id,
+            description: fields[..padding_start].to_vec(),
+            currency: *BinRecord::DEFAULT_CURRENCY,
+            fee: [0; 8],
+            reference: vec![],
+        });
+        self.verify_checksum(r, record.id, hash)?;
+
+        Ok(Some(record))
     }
 }
 
-pub(crate) struct BinRecordWriter;
+/// Writer of binary records
+pub struct BinRecordWriter {
+    framing: Framing,
+    checksums: bool,
+    /// Fixed width integer fields are little-endian
+    little_endian: bool,
+    /// Write file header with number of records before records
+    count_header: bool,
+}
 
 impl BinRecordWriter {
-    pub(crate) fn new() -> Self {
-        Self
+    /// Writer of records with header and length
+    pub fn new() -> Self {
+        Self {
+            framing: Framing::Headered,
+            checksums: false,
+            little_endian: false,
+            count_header: false,
+        }
+    }
+
+    /// Writer appending CRC32 of record body to every record if `checksums` is set
+    pub fn with_checksums(checksums: bool) -> Self {
+        Self::new().checksums(checksums)
+    }
+
+    /// Writer starting file with number of records if `count_header` is set, readers can preallocate records by it
+    pub fn with_count_header(count_header: bool) -> Self {
+        Self::new().count_header(count_header)
+    }
+
+    /// Append CRC32 of record body to every record if `checksums` is set
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Write fixed width integer fields and description length in little-endian byte order if `little_endian` is set
+    pub fn little_endian(mut self, little_endian: bool) -> Self {
+        self.little_endian = little_endian;
+        self
+    }
+
+    /// Start file with number of records if `count_header` is set, readers can preallocate records by it
+    pub fn count_header(mut self, count_header: bool) -> Self {
+        self.count_header = count_header;
+        self
+    }
+
+    /// Write records without header and length, descriptions are zero padded to `description_len` bytes
+    pub fn headerless(mut self, description_len: usize) -> Self {
+        self.framing = Framing::Headerless { description_len };
+        self
+    }
+
+    /// Write records with LEB128 encoded integer fields
+    pub fn varint(mut self) -> Self {
+        self.framing = Framing::Varint;
+        self
+    }
+}
+
+impl Default for BinRecordWriter {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl RecordWriter for BinRecordWriter {
-    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<(), YpbankError> {
+    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<()> {
+        if self.count_header && matches!(self.framing, Framing::Headered) {
+            let mut header = BinRecord::HEADER.to_vec();
+            header.push(BinRecord::COUNT_HEADER_VERSION);
+            header.extend_from_slice(&(records.len() as u64).to_be_bytes());
+            w.write_all(&header)
+                .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+        }
+
         for record in records {
-            let bin_record = BinRecord::from(record);
+            let mut bin_record = BinRecord::from(record);
+            // LEB128 fields have no byte order
+            if self.little_endian && !matches!(self.framing, Framing::Varint) {
+                bin_record.swap_byte_order();
+            }
 
-            let mut buffer = vec![];
+            let (header, mut buffer) = match self.framing {
+                Framing::Headered => (
+                    Some(BinRecord::HEADER),
+                    bin_record.encode_fixed(self.little_endian)?,
+                ),
+                Framing::Varint => (Some(BinRecord::VARINT_HEADER), bin_record.encode_varint()?),
+                Framing::Headerless { description_len } => {
+                    (None, bin_record.encode_headerless(description_len)?)
+                }
+            };
+            if self.checksums {
+                let checksum = crc32fast::hash(&buffer);
+                buffer.extend_from_slice(&checksum.to_be_bytes());
+            }
 
-            buffer.extend_from_slice(&bin_record.id);
-            buffer.push(bin_record.record_type);
-            buffer.extend_from_slice(&bin_record.from_user_id);
-            buffer.extend_from_slice(&bin_record.to_user_id);
-            buffer.extend_from_slice(&bin_record.amount);
-            buffer.extend_from_slice(&bin_record.timestamp);
-            buffer.push(bin_record.status);
-            buffer.extend_from_slice(&(bin_record.description.len() as u32).to_be_bytes());
-            buffer.extend_from_slice(&bin_record.description);
-
-            w.write_all(BinRecord::HEADER)
-                .map_err(|e| YpbankError::WriteError(e.to_string()))?;
-            w.write_all(&(buffer.len() as u32).to_be_bytes())
-                .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+            if let Some(header) = header {
+                w.write_all(header)
+                    .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+                w.write_all(&[BinRecord::VERSION])
+                    .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+                w.write_all(&(buffer.len() as u32).to_be_bytes())
+                    .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+            }
             w.write_all(&buffer)
                 .map_err(|e| YpbankError::WriteError(e.to_string()))?;
         }
@@ -152,17 +788,392 @@ struct BinRecord {
     amount: [u8; 8],
     timestamp: [u8; 8],
     status: u8,
+    /// Present only for reversals, stored right after status
+    original_tx_id: Option<[u8; 8]>,
     description: Vec<u8>,
+    /// ISO 4217 code stored after description since version 3
+    currency: [u8; 3],
+    /// Stored after currency since version 4
+    fee: [u8; 8],
+    /// Stored after fee with its length since version 5
+    reference: Vec<u8>,
 }
 
 impl BinRecord {
     const HEADER: &[u8; 4] = b"YPBN";
+
+    /// Header of record with LEB128 encoded integer fields
+    const VARINT_HEADER: &[u8; 4] = b"YPBV";
+
+    /// Format version written right after header, records of this version end with currency code, fee and reference.
+    ///
+    /// Records without version are still readable if their length is below 16 MiB,
+    /// since zero version byte is treated as the high byte of legacy record length
+    const VERSION: u8 = 5;
+
+    /// Version of records ending with currency code and fee, they are read with empty reference
+    const FEE_VERSION: u8 = 4;
+
+    /// Version of records ending with currency code only, they are read with zero fee
+    const CURRENCY_VERSION: u8 = 3;
+
+    /// Version of records written before currency was added, they are read in [`Record::DEFAULT_CURRENCY`]
+    const NO_CURRENCY_VERSION: u8 = 1;
+
+    /// Currency of records without currency code
+    const DEFAULT_CURRENCY: &[u8; 3] = b"XXX";
+
+    /// Length of currency code following description
+    const CURRENCY_LENGTH: u32 = 3;
+
+    /// Length of fee following currency code
+    const FEE_LENGTH: u32 = 8;
+
+    /// Version byte of file header carrying number of records instead of record length.
+    ///
+    /// Readers unaware of it reject the file as written by newer version of format
+    const COUNT_HEADER_VERSION: u8 = 2;
+
+    /// Length of fixed size record fields
+    const FIELDS_LENGTH: u32 = 8 + 1 + 8 + 8 + 8 + 8 + 1;
+
+    /// Length of all record fields except description bytes
+    const FIXED_PART_LENGTH: u32 = Self::FIELDS_LENGTH + 4;
+
+    /// Type of reversal record, the only type carrying original transaction id.
+    ///
+    /// Readers unaware of this type reject it as unexpected value instead of misreading the layout
+    const REVERSAL_TYPE: u8 = 4;
+
+    /// Check if records of given version store currency code
+    fn has_currency(version: u8) -> bool {
+        matches!(
+            version,
+            Self::VERSION | Self::FEE_VERSION | Self::CURRENCY_VERSION
+        )
+    }
+
+    /// Check if records of given version store fee
+    fn has_fee(version: u8) -> bool {
+        matches!(version, Self::VERSION | Self::FEE_VERSION)
+    }
+
+    /// Length of fields following description in records of given version with fixed width integers,
+    /// reference bytes are not included
+    fn trailer_length(version: u8) -> u32 {
+        match version {
+            Self::VERSION => Self::CURRENCY_LENGTH + Self::FEE_LENGTH + 4,
+            Self::FEE_VERSION => Self::CURRENCY_LENGTH + Self::FEE_LENGTH,
+            Self::CURRENCY_VERSION => Self::CURRENCY_LENGTH,
+            _ => 0,
+        }
+    }
+
+    /// Length of fields following status which are present only for some record types
+    fn extension_length(record_type: u8) -> u32 {
+        if record_type == Self::REVERSAL_TYPE {
+            8
+        } else {
+            0
+        }
+    }
+
+    /// Convert description length to its binary representation, whole record length must fit into u32
+    fn description_length(len: usize) -> Result<u32> {
+        u32::try_from(len)
+            .ok()
+            .filter(|len| len.checked_add(Self::FIXED_PART_LENGTH).is_some())
+            .ok_or(YpbankError::BinaryDescriptionTooLong)
+    }
+
+    /// Reverse byte order of fixed width integer fields
+    fn swap_byte_order(&mut self) {
+        for field in [
+            &mut self.id,
+            &mut self.from_user_id,
+            &mut self.to_user_id,
+            &mut self.amount,
+            &mut self.timestamp,
+            &mut self.fee,
+        ]
+        .into_iter()
+        .chain(self.original_tx_id.as_mut())
+        {
+            field.reverse();
+        }
+    }
+
+    /// Append fixed size fields to buffer
+    fn encode_fields(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.id);
+        buffer.push(self.record_type);
+        buffer.extend_from_slice(&self.from_user_id);
+        buffer.extend_from_slice(&self.to_user_id);
+        buffer.extend_from_slice(&self.amount);
+        buffer.extend_from_slice(&self.timestamp);
+        buffer.push(self.status);
+        if let Some(original_tx_id) = &self.original_tx_id {
+            buffer.extend_from_slice(original_tx_id);
+        }
+    }
+
+    /// Encode record body with fixed width integer fields, description length is little-endian if `little_endian` is set
+    fn encode_fixed(&self, little_endian: bool) -> Result<Vec<u8>> {
+        let mut buffer = vec![];
+
+        self.encode_fields(&mut buffer);
+        let description_length = Self::description_length(self.description.len())?;
+        buffer.extend_from_slice(&if little_endian {
+            description_length.to_le_bytes()
+        } else {
+            description_length.to_be_bytes()
+        });
+        buffer.extend_from_slice(&self.description);
+        buffer.extend_from_slice(&self.currency);
+        buffer.extend_from_slice(&self.fee);
+        let reference_length = Self::description_length(self.reference.len())?;
+        buffer.extend_from_slice(&if little_endian {
+            reference_length.to_le_bytes()
+        } else {
+            reference_length.to_be_bytes()
+        });
+        buffer.extend_from_slice(&self.reference);
+
+        if u32::try_from(buffer.len()).is_err() {
+            return Err(YpbankError::BinaryDescriptionTooLong);
+        }
+
+        Ok(buffer)
+    }
+
+    /// Encode headerless frame with description zero padded to `description_len` bytes
+    fn encode_headerless(&self, description_len: usize) -> Result<Vec<u8>> {
+        if self.description.len() > description_len {
+            return Err(YpbankError::BinaryDescriptionTooLong);
+        }
+        // Frame layout has no room for currency and fee, only records without them can be stored
+        if self.currency != *Self::DEFAULT_CURRENCY {
+            return Err(YpbankError::WriteError(format!(
+                "headerless record {} can't store currency {}",
+                u64::from_be_bytes(self.id),
+                String::from_utf8_lossy(&self.currency)
+            )));
+        }
+        if self.fee != [0; 8] || !self.reference.is_empty() {
+            return Err(YpbankError::WriteError(format!(
+                "headerless record {} can't store fee or reference",
+                u64::from_be_bytes(self.id)
+            )));
+        }
+
+        let mut buffer = vec![];
+
+        self.encode_fields(&mut buffer);
+        buffer.extend_from_slice(&self.description);
+        buffer.resize(buffer.len() + description_len - self.description.len(), 0);
+
+        Ok(buffer)
+    }
+
+    /// Encode record body with LEB128 integer fields
+    fn encode_varint(&self) -> Result<Vec<u8>> {
+        let mut buffer = vec![];
+
+        write_varint(&mut buffer, u64::from_be_bytes(self.id));
+        buffer.push(self.record_type);
+        write_varint(&mut buffer, u64::from_be_bytes(self.from_user_id));
+        write_varint(&mut buffer, u64::from_be_bytes(self.to_user_id));
+        write_varint(&mut buffer, u64::from_be_bytes(self.amount));
+        write_varint(&mut buffer, u64::from_be_bytes(self.timestamp));
+        buffer.push(self.status);
+        if let Some(original_tx_id) = self.original_tx_id {
+            write_varint(&mut buffer, u64::from_be_bytes(original_tx_id));
+        }
+        write_varint(&mut buffer, self.description.len() as u64);
+        buffer.extend_from_slice(&self.description);
+        buffer.extend_from_slice(&self.currency);
+        write_varint(&mut buffer, u64::from_be_bytes(self.fee));
+        write_varint(&mut buffer, self.reference.len() as u64);
+        buffer.extend_from_slice(&self.reference);
+
+        if u32::try_from(buffer.len()).is_err() {
+            return Err(YpbankError::BinaryDescriptionTooLong);
+        }
+
+        Ok(buffer)
+    }
+
+    /// Decode record body with LEB128 integer fields
+    fn decode_varint(body: &[u8], max_description_len: usize, version: u8) -> Result<Self> {
+        let mut rest = body;
+
+        let id = read_varint(&mut rest)?.to_be_bytes();
+        let record_type = read_n_bytes!(rest, 1)?[0];
+        let from_user_id = read_varint(&mut rest)?.to_be_bytes();
+        let to_user_id = read_varint(&mut rest)?.to_be_bytes();
+        let amount = read_varint(&mut rest)?.to_be_bytes();
+        let timestamp = read_varint(&mut rest)?.to_be_bytes();
+        let status = read_n_bytes!(rest, 1)?[0];
+        let original_tx_id = if record_type == Self::REVERSAL_TYPE {
+            Some(read_varint(&mut rest)?.to_be_bytes())
+        } else {
+            None
+        };
+        let description_length = read_varint(&mut rest)?;
+
+        if (rest.len() as u64) < description_length
+            || description_length > max_description_len as u64
+        {
+            return Err(YpbankError::BinaryDescriptionTooLong);
+        }
+        let (description, mut rest) = rest.split_at(description_length as usize);
+        let currency = if Self::has_currency(version) {
+            read_n_bytes!(rest, 3)?
+        } else {
+            *Self::DEFAULT_CURRENCY
+        };
+        let fee = if Self::has_fee(version) {
+            read_varint(&mut rest)?.to_be_bytes()
+        } else {
+            [0; 8]
+        };
+        let reference = if version == Self::VERSION {
+            let reference_length = read_varint(&mut rest)?;
+            if (rest.len() as u64) < reference_length
+                || reference_length > max_description_len as u64
+            {
+                return Err(YpbankError::BinaryDescriptionTooLong);
+            }
+            let (reference, tail) = rest.split_at(reference_length as usize);
+            rest = tail;
+            reference.to_vec()
+        } else {
+            vec![]
+        };
+        if !rest.is_empty() {
+            let record_length = body.len() as u32;
+            return Err(YpbankError::BinaryLengthMismatch(
+                record_length,
+                record_length - rest.len() as u32,
+            ));
+        }
+
+        Ok(Self {
+            id,
+            record_type,
+            from_user_id,
+            to_user_id,
+            amount,
+            timestamp,
+            status,
+            original_tx_id,
+            description: description.to_vec(),
+            currency,
+            fee,
+            reference,
+        })
+    }
+}
+
+/// Read exactly `len` bytes, buffer grows only as data arrives so bogus lengths cannot exhaust memory
+fn read_bytes(r: &mut dyn Read, len: usize) -> Result<Vec<u8>> {
+    let mut buffer = vec![];
+    r.take(len as u64)
+        .read_to_end(&mut buffer)
+        .map_err(|e| YpbankError::BinaryReadError(ErrorSource::new(e)))?;
+
+    if buffer.len() < len {
+        return Err(YpbankError::BinaryRecordTooShort);
+    }
+    Ok(buffer)
+}
+
+/// Item of headered binary stream
+enum Frame {
+    /// Number of records following file header
+    Count(u64),
+    Record(BinRecord),
+}
+
+/// Value following header and version byte
+enum FrameLength {
+    /// Length of record body and its version, zero for legacy records
+    Record { length: u32, version: u8 },
+    /// Number of records in file
+    Count(u64),
+}
+
+/// Read version byte and length of record or record count following header
+fn read_frame_length(r: &mut dyn Read) -> Result<FrameLength> {
+    match read_n_bytes!(r, 1)?[0] {
+        version @ (BinRecord::VERSION
+        | BinRecord::FEE_VERSION
+        | BinRecord::CURRENCY_VERSION
+        | BinRecord::NO_CURRENCY_VERSION) => Ok(FrameLength::Record {
+            length: u32::from_be_bytes(read_n_bytes!(r, 4)?),
+            version,
+        }),
+        BinRecord::COUNT_HEADER_VERSION => {
+            Ok(FrameLength::Count(u64::from_be_bytes(read_n_bytes!(r, 8)?)))
+        }
+        // Legacy records have no version byte, it is the high byte of record length
+        0 => {
+            let [b1, b2, b3] = read_n_bytes!(r, 3)?;
+            Ok(FrameLength::Record {
+                length: u32::from_be_bytes([0, b1, b2, b3]),
+                version: 0,
+            })
+        }
+        version => Err(YpbankError::UnsupportedBinaryVersion(version)),
+    }
+}
+
+/// Append value to buffer as unsigned LEB128
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer.push(byte);
+            return;
+        }
+        buffer.push(byte | 0x80);
+    }
+}
+
+/// Read unsigned LEB128 value and advance slice past it
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or(YpbankError::BinaryRecordTooShort)?;
+        *bytes = rest;
+
+        let payload = u64::from(byte & 0x7f);
+        if shift == 63 && payload > 1 {
+            return Err(YpbankError::BinaryUnexpectedValue);
+        }
+        value |= payload << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(YpbankError::BinaryUnexpectedValue)
 }
 
 impl TryInto<Record> for BinRecord {
     type Error = YpbankError;
 
     fn try_into(self) -> Result<Record, Self::Error> {
+        self.into_record(false)
+    }
+}
+
+impl BinRecord {
+    /// Convert to record, nonzero user id unused by record type is an error if `reject_unused_user_ids` is set
+    fn into_record(self, reject_unused_user_ids: bool) -> Result<Record> {
         let id = u64::from_be_bytes(self.id);
         let from_user_id = u64::from_be_bytes(self.from_user_id);
         let to_user_id = u64::from_be_bytes(self.to_user_id);
@@ -173,41 +1184,60 @@ impl TryInto<Record> for BinRecord {
                 to_user_id,
             },
             2 => RecordType::Withdrawal { from_user_id },
+            3 => RecordType::Fee { from_user_id },
+            Self::REVERSAL_TYPE => RecordType::Reversal {
+                from_user_id,
+                to_user_id,
+                original_tx_id: self
+                    .original_tx_id
+                    .map(u64::from_be_bytes)
+                    .ok_or(YpbankError::BinaryUnexpectedValue)?,
+            },
             _ => return Err(YpbankError::BinaryUnexpectedValue),
         };
+        if reject_unused_user_ids {
+            record_type.check_unused_user_id(id, from_user_id, to_user_id)?;
+        }
         let amount = u64::from_be_bytes(self.amount);
         let timestamp = u64::from_be_bytes(self.timestamp);
         let status = match self.status {
             0 => RecordStatus::Success,
             1 => RecordStatus::Failure,
             2 => RecordStatus::Pending,
+            3 => RecordStatus::Cancelled,
             _ => return Err(YpbankError::BinaryUnexpectedValue),
         };
-        let description = if let Ok(str) = String::from_utf8(self.description) {
-            str
+        let description = if self.description.is_empty() {
+            None
+        } else if let Ok(str) = String::from_utf8(self.description) {
+            Some(str)
         } else {
             return Err(YpbankError::BinaryUnexpectedValue);
         };
-        Ok(Record::new(
-            id,
-            record_type,
-            amount,
-            timestamp,
-            status,
-            description,
-        ))
+        let currency =
+            std::str::from_utf8(&self.currency).map_err(|_| YpbankError::BinaryUnexpectedValue)?;
+        let reference =
+            String::from_utf8(self.reference).map_err(|_| YpbankError::BinaryUnexpectedValue)?;
+        Record::new(id, record_type, amount, timestamp, status, description)
+            .with_currency(currency)
+            .map(|record| {
+                record
+                    .with_fee(u64::from_be_bytes(self.fee))
+                    .with_reference(reference)
+            })
+            .map_err(|_| YpbankError::BinaryUnexpectedValue)
     }
 }
 
 impl From<&Record> for BinRecord {
     fn from(value: &Record) -> Self {
-        let (record_type, from_user_id, to_user_id) = match value.record_type {
-            RecordType::Deposit { to_user_id } => (0, 0, to_user_id),
-            RecordType::Withdrawal { from_user_id } => (2, from_user_id, 0),
-            RecordType::Transfer {
-                from_user_id,
-                to_user_id,
-            } => (1, from_user_id, to_user_id),
+        let (from_user_id, to_user_id) = value.record_type.endpoints();
+        let record_type = match value.record_type.kind() {
+            RecordKind::Deposit => 0,
+            RecordKind::Transfer => 1,
+            RecordKind::Withdrawal => 2,
+            RecordKind::Fee => 3,
+            RecordKind::Reversal => Self::REVERSAL_TYPE,
         };
 
         Self {
@@ -217,12 +1247,1022 @@ impl From<&Record> for BinRecord {
             to_user_id: to_user_id.to_be_bytes(),
             amount: value.amount.to_be_bytes(),
             timestamp: value.timestamp.to_be_bytes(),
+            original_tx_id: value.record_type.original_tx_id().map(u64::to_be_bytes),
             status: match value.status {
                 RecordStatus::Success => 0,
                 RecordStatus::Failure => 1,
                 RecordStatus::Pending => 2,
+                RecordStatus::Cancelled => 3,
             },
-            description: value.description.as_bytes().to_vec(),
+            description: value
+                .description
+                .as_ref()
+                .map_or_else(Vec::new, |d| d.as_bytes().to_vec()),
+            currency: value.currency,
+            fee: value.fee.to_be_bytes(),
+            reference: value.reference.as_bytes().to_vec(),
+        }
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_from_record_round_trip() {
+        let records = vec![
+            Record::new(
+                1,
+                RecordType::Deposit { to_user_id: 501 },
+                u64::MAX,
+                1672531200000,
+                RecordStatus::Success,
+                "Quoted \"savings\", \\ and\nnew line".to_string(),
+            ),
+            Record::new(
+                2,
+                RecordType::Reversal {
+                    from_user_id: 502,
+                    to_user_id: 501,
+                    original_tx_id: 1,
+                },
+                100,
+                1672534800000,
+                RecordStatus::Cancelled,
+                None,
+            ),
+        ];
+
+        for record in records {
+            let result: Result<Record> = BinRecord::from(&record).try_into();
+            assert_eq!(result, Ok(record));
+        }
+    }
+
+    #[test]
+    fn test_read_all_length_mismatch() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let header_len = BinRecord::HEADER.len() + 1;
+        let length_bytes = &mut buffer[header_len..header_len + 4];
+        let declared = u32::from_be_bytes(length_bytes.try_into().expect("Should be 4 bytes"));
+        length_bytes.copy_from_slice(&(declared + 3).to_be_bytes());
+        buffer.extend_from_slice(&[0, 0, 0]);
+
+        let result = BinRecordReader::new().read_all(&mut Cursor::new(buffer));
+
+        assert_eq!(
+            result,
+            Err(YpbankError::BinaryLengthMismatch(declared + 3, declared))
+        )
+    }
+
+    #[test]
+    fn test_read_trailing_bytes_strict_and_lenient() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        for trailing in [&b"YP"[..], &b"\0\0\0\0\0"[..]] {
+            let mut data = buffer.clone();
+            data.extend_from_slice(trailing);
+
+            let strict = BinRecordReader::with_config(ReaderConfig::strict())
+                .read_all(&mut Cursor::new(&data));
+            assert_eq!(strict, Err(YpbankError::BinaryTrailingData));
+
+            let lenient = BinRecordReader::with_config(ReaderConfig::lenient())
+                .read_all(&mut Cursor::new(&data));
+            assert_eq!(lenient, Ok(records.clone()));
+        }
+    }
+
+    #[test]
+    fn test_read_garbage_header() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Withdrawal { from_user_id: 501 },
+                1000,
+                1672534800000,
+                RecordStatus::Success,
+                "ATM withdrawal".to_string(),
+            ),
+        ];
+        let csv_data =
+            b"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION\n";
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+        let second_record = buffer
+            .windows(BinRecord::HEADER.len())
+            .rposition(|window| window == BinRecord::HEADER)
+            .expect("Second record should have header");
+        let mut corrupted = buffer.clone();
+        corrupted[second_record] = b'X';
+
+        for config in [ReaderConfig::strict(), ReaderConfig::lenient()] {
+            let reader = BinRecordReader::with_config(config);
+            assert_eq!(
+                reader.read_all(&mut Cursor::new(&csv_data[..])),
+                Err(YpbankError::BinaryUnexpectedValue)
+            );
+            assert_eq!(
+                reader.read_all(&mut Cursor::new(&b"YP"[..])),
+                Err(YpbankError::BinaryUnexpectedValue)
+            );
+
+            let mut data = buffer[..second_record].to_vec();
+            data.extend_from_slice(&corrupted[second_record..]);
+            data.extend_from_slice(&buffer[second_record..]);
+            assert_eq!(
+                reader.read_all(&mut Cursor::new(&data)),
+                Err(YpbankError::BinaryUnexpectedValue)
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_matches_read_all() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1006,
+                RecordType::Reversal {
+                    from_user_id: 502,
+                    to_user_id: 501,
+                    original_tx_id: 1002,
+                },
+                15000,
+                1672549200000,
+                RecordStatus::Success,
+                None,
+            ),
+        ];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+        BinRecordWriter::new()
+            .varint()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let reader = BinRecordReader::new();
+        let read = reader
+            .read_all(&mut Cursor::new(&buffer))
+            .expect("Should read successfully");
+        assert_eq!(reader.count(&mut Cursor::new(&buffer)), Ok(read.len()));
+        assert_eq!(read.len(), 4);
+
+        buffer.pop();
+        assert_eq!(
+            reader.count(&mut Cursor::new(&buffer)),
+            Err(YpbankError::BinaryRecordTooShort)
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_utf8_description() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Cafe".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+        let description_end = buffer.len() - BinRecord::trailer_length(BinRecord::VERSION) as usize;
+        buffer[description_end - 1] = 0xff;
+
+        let strict = BinRecordReader::new().read_all(&mut Cursor::new(&buffer));
+        assert_eq!(strict, Err(YpbankError::BinaryUnexpectedValue));
+
+        let lossy = BinRecordReader::new()
+            .lossy(true)
+            .read_all(&mut Cursor::new(&buffer))
+            .expect("Should read successfully");
+        assert_eq!(lossy[0].description(), Some("Caf\u{fffd}"));
+    }
+
+    #[test]
+    fn test_read_clean_end_and_trailing_data() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let reader = BinRecordReader::new();
+        assert_eq!(
+            reader.read_all(&mut Cursor::new(&buffer)),
+            Ok(records.clone())
+        );
+
+        for junk in [&b"Y"[..], &b"YPB"[..], &b"junk data"[..]] {
+            let mut data = buffer.clone();
+            data.extend_from_slice(junk);
+            assert_eq!(
+                reader.read_all(&mut Cursor::new(&data)),
+                Err(YpbankError::BinaryTrailingData),
+                "junk {junk:?}"
+            );
+        }
+
+        let mut headerless = vec![];
+        BinRecordWriter::new()
+            .headerless(32)
+            .write_all(&mut headerless, &records)
+            .expect("Should write successfully");
+        headerless.extend_from_slice(&[0; 7]);
+        assert_eq!(
+            BinRecordReader::new()
+                .headerless(32)
+                .read_all(&mut Cursor::new(&headerless)),
+            Err(YpbankError::BinaryTrailingData)
+        );
+    }
+
+    #[test]
+    fn test_combined_options_round_trip() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            )
+            .with_fee(25)
+            .with_reference("INV-1"),
+        ];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .little_endian(true)
+            .checksums(true)
+            .count_header(true)
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        assert_eq!(
+            BinRecordReader::new()
+                .little_endian(true)
+                .checksums(true)
+                .lossy(true)
+                .read_all(&mut Cursor::new(&buffer)),
+            Ok(records.clone())
+        );
+        assert!(
+            BinRecordReader::new()
+                .checksums(true)
+                .read_all(&mut Cursor::new(&buffer))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_little_endian_round_trip() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1006,
+                RecordType::Reversal {
+                    from_user_id: 502,
+                    to_user_id: 501,
+                    original_tx_id: 1002,
+                },
+                15000,
+                1672549200000,
+                RecordStatus::Success,
+                None,
+            ),
+        ];
+
+        let mut little = vec![];
+        BinRecordWriter::new()
+            .little_endian(true)
+            .write_all(&mut little, &records)
+            .expect("Should write successfully");
+        let mut big = vec![];
+        BinRecordWriter::new()
+            .little_endian(false)
+            .write_all(&mut big, &records)
+            .expect("Should write successfully");
+
+        assert_ne!(little, big);
+        assert_eq!(little.len(), big.len());
+        let id_start = BinRecord::HEADER.len() + 1 + 4;
+        assert_eq!(little[id_start..id_start + 8], 1001u64.to_le_bytes());
+
+        assert_eq!(
+            BinRecordReader::new()
+                .little_endian(true)
+                .read_all(&mut Cursor::new(&little)),
+            Ok(records.clone())
+        );
+        assert_eq!(
+            BinRecordReader::new().read_all(&mut Cursor::new(&big)),
+            Ok(records.clone())
+        );
+        assert_ne!(
+            BinRecordReader::new().read_all(&mut Cursor::new(&little)),
+            Ok(records)
+        );
+    }
+
+    #[test]
+    fn test_count_header() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Withdrawal { from_user_id: 501 },
+                1000,
+                1672534800000,
+                RecordStatus::Pending,
+                None,
+            ),
+        ];
+
+        let mut with_header = vec![];
+        BinRecordWriter::with_count_header(true)
+            .write_all(&mut with_header, &records)
+            .expect("Should write successfully");
+        let mut legacy = vec![];
+        BinRecordWriter::with_count_header(false)
+            .write_all(&mut legacy, &records)
+            .expect("Should write successfully");
+
+        let mut header = BinRecord::HEADER.to_vec();
+        header.push(BinRecord::COUNT_HEADER_VERSION);
+        header.extend_from_slice(&2u64.to_be_bytes());
+        assert_eq!(with_header, [header, legacy.clone()].concat());
+
+        let reader = BinRecordReader::new();
+        for data in [&with_header, &legacy] {
+            assert_eq!(reader.read_all(&mut Cursor::new(data)), Ok(records.clone()));
+            assert_eq!(
+                reader
+                    .records(&mut Cursor::new(data))
+                    .collect::<Result<Vec<_>, _>>(),
+                Ok(records.clone())
+            );
+            assert_eq!(reader.count(&mut Cursor::new(data)), Ok(2));
+        }
+
+        // Count is taken from header without reading records
+        let truncated = &with_header[..BinRecord::HEADER.len() + 9];
+        assert_eq!(reader.count(&mut Cursor::new(truncated)), Ok(2));
+    }
+
+    #[test]
+    fn test_index_and_read_record_at() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                "Payment for services".to_string(),
+            ),
+            Record::new(
+                1003,
+                RecordType::Withdrawal { from_user_id: 502 },
+                1000,
+                1672538400000,
+                RecordStatus::Pending,
+                None,
+            ),
+        ];
+
+        let mut buffer = vec![];
+        BinRecordWriter::with_count_header(true)
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+        let mut cursor = Cursor::new(buffer);
+
+        let index = build_index(&mut cursor).expect("Should build index");
+        assert_eq!(
+            index.keys().copied().collect::<Vec<_>>(),
+            vec![1001, 1002, 1003]
+        );
+        assert_eq!(index[&1001], 13);
+
+        assert_eq!(
+            read_record_at(&mut cursor, index[&1002]),
+            Ok(records[1].clone())
+        );
+        let end = cursor.get_ref().len() as u64;
+        assert!(read_record_at(&mut cursor, end).is_err());
+    }
+
+    #[test]
+    fn test_read_one_raw() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::with_count_header(true)
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+        let mut cursor = Cursor::new(&buffer);
+
+        let header = read_one_raw(&mut cursor)
+            .expect("Should read header")
+            .expect("Should have header");
+        assert!(header.is_count_header());
+        assert_eq!(header.declared_length, 1);
+        assert_eq!(header.decode(), Ok(None));
+
+        let frame = read_one_raw(&mut cursor)
+            .expect("Should read record")
+            .expect("Should have record");
+        assert_eq!(&frame.magic, BinRecord::HEADER);
+        assert_eq!(frame.version, BinRecord::VERSION);
+        assert_eq!(frame.bytes, buffer[header.bytes.len()..]);
+        assert_eq!(frame.declared_length as usize, frame.bytes.len() - 9);
+        assert_eq!(frame.decode(), Ok(Some(records[0].clone())));
+
+        assert_eq!(read_one_raw(&mut cursor), Ok(None));
+    }
+
+    #[test]
+    fn test_read_huge_description_length() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let result = BinRecordReader::with_max_description_len(8).read_all(&mut &buffer[..]);
+        assert_eq!(result, Err(YpbankError::BinaryDescriptionTooLong));
+
+        let header_len = BinRecord::HEADER.len() + 1;
+        buffer[header_len..header_len + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let description_len_at = header_len + 4 + BinRecord::FIELDS_LENGTH as usize;
+        buffer[description_len_at..description_len_at + 4].copy_from_slice(
+            &(u32::MAX
+                - BinRecord::FIXED_PART_LENGTH
+                - BinRecord::trailer_length(BinRecord::VERSION))
+            .to_be_bytes(),
+        );
+
+        let result = BinRecordReader::new().read_all(&mut &buffer[..]);
+        assert_eq!(result, Err(YpbankError::BinaryDescriptionTooLong));
+
+        let result =
+            BinRecordReader::with_max_description_len(usize::MAX).read_all(&mut &buffer[..]);
+        assert_eq!(result, Err(YpbankError::BinaryRecordTooShort));
+    }
+
+    #[test]
+    fn test_read_length_without_trailer() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            None,
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        // Declared length covers fixed part only, fields following description are missing
+        let version_at = BinRecord::HEADER.len();
+        let mut truncated =
+            buffer[..version_at + 5 + BinRecord::FIXED_PART_LENGTH as usize].to_vec();
+        truncated[version_at + 1..version_at + 5]
+            .copy_from_slice(&BinRecord::FIXED_PART_LENGTH.to_be_bytes());
+        for version in [
+            BinRecord::VERSION,
+            BinRecord::FEE_VERSION,
+            BinRecord::CURRENCY_VERSION,
+        ] {
+            truncated[version_at] = version;
+            assert_eq!(
+                BinRecordReader::new().read_all(&mut &truncated[..]),
+                Err(YpbankError::BinaryRecordTooShort),
+                "version {version}"
+            );
         }
     }
+
+    #[test]
+    fn test_read_unknown_status() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Cancelled,
+            "Initial account funding".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let status_at = BinRecord::HEADER.len() + 1 + 4 + BinRecord::FIELDS_LENGTH as usize - 1;
+        assert_eq!(buffer[status_at], 3);
+        buffer[status_at] = 4;
+
+        let result = BinRecordReader::new().read_all(&mut &buffer[..]);
+        assert_eq!(result, Err(YpbankError::BinaryUnexpectedValue));
+    }
+
+    #[test]
+    fn test_read_unknown_record_type() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Fee { from_user_id: 501 },
+            150,
+            1672531200000,
+            RecordStatus::Success,
+            "Monthly fee".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let type_at = BinRecord::HEADER.len() + 1 + 4 + 8;
+        assert_eq!(buffer[type_at], 3);
+        buffer[type_at] = 5;
+
+        let result = BinRecordReader::new().read_all(&mut &buffer[..]);
+        assert_eq!(result, Err(YpbankError::BinaryUnexpectedValue));
+    }
+
+    #[test]
+    fn test_reversal_round_trip_all_framings() {
+        let records = vec![
+            Record::new(
+                1006,
+                RecordType::Reversal {
+                    from_user_id: 502,
+                    to_user_id: 501,
+                    original_tx_id: 1002,
+                },
+                15000,
+                1672549200000,
+                RecordStatus::Success,
+                "Chargeback".to_string(),
+            ),
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Deposit".to_string(),
+            ),
+        ];
+
+        for (writer, reader) in [
+            (BinRecordWriter::new(), BinRecordReader::new()),
+            (BinRecordWriter::new().varint(), BinRecordReader::new()),
+            (
+                BinRecordWriter::new().headerless(16),
+                BinRecordReader::new().headerless(16),
+            ),
+        ] {
+            let mut buffer = vec![];
+            writer
+                .write_all(&mut buffer, &records)
+                .expect("Should write successfully");
+
+            assert_eq!(reader.read_all(&mut &buffer[..]), Ok(records.clone()));
+        }
+    }
+
+    #[test]
+    fn test_version_byte() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let version_at = BinRecord::HEADER.len();
+        assert_eq!(buffer[version_at], BinRecord::VERSION);
+        assert_eq!(
+            BinRecordReader::new().read_all(&mut &buffer[..]),
+            Ok(records.clone())
+        );
+
+        // Older versions lack fields following description, reference, fee and then currency code
+        let downgrade = |buffer: &[u8], version: u8| {
+            let mut older = buffer[..buffer.len()
+                - BinRecord::trailer_length(buffer[version_at]) as usize
+                + BinRecord::trailer_length(version) as usize]
+                .to_vec();
+            older[version_at] = version;
+            let length = (older.len() - version_at - 5) as u32;
+            older[version_at + 1..version_at + 5].copy_from_slice(&length.to_be_bytes());
+            older
+        };
+        let no_reference = downgrade(&buffer, BinRecord::FEE_VERSION);
+        let no_fee = downgrade(&no_reference, BinRecord::CURRENCY_VERSION);
+        let no_currency = downgrade(&no_fee, BinRecord::NO_CURRENCY_VERSION);
+        for older in [&no_reference, &no_fee, &no_currency] {
+            assert_eq!(
+                BinRecordReader::new().read_all(&mut &older[..]),
+                Ok(records.clone())
+            );
+        }
+
+        let mut legacy = no_currency.clone();
+        legacy.remove(version_at);
+        assert_eq!(
+            BinRecordReader::new().read_all(&mut &legacy[..]),
+            Ok(records)
+        );
+
+        buffer[version_at] = 6;
+        assert_eq!(
+            BinRecordReader::new().read_all(&mut &buffer[..]),
+            Err(YpbankError::UnsupportedBinaryVersion(6))
+        );
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                "Payment for services".to_string(),
+            ),
+        ];
+
+        let mut buffer = vec![];
+        BinRecordWriter::with_checksums(true)
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        assert_eq!(
+            BinRecordReader::with_checksums(true).read_all(&mut &buffer[..]),
+            Ok(records)
+        );
+        assert!(matches!(
+            BinRecordReader::new().read_all(&mut &buffer[..]),
+            Err(YpbankError::BinaryLengthMismatch(_, _))
+        ));
+
+        let second_record_at = BinRecord::HEADER.len()
+            + 1
+            + 4
+            + BinRecord::FIXED_PART_LENGTH as usize
+            + "Initial account funding".len()
+            + BinRecord::trailer_length(BinRecord::VERSION) as usize
+            + 4;
+        assert!(buffer[second_record_at..].starts_with(BinRecord::HEADER));
+        let amount_at = second_record_at + BinRecord::HEADER.len() + 1 + 4 + 8 + 1 + 8 + 8;
+        buffer[amount_at] ^= 0xff;
+
+        assert_eq!(
+            BinRecordReader::with_checksums(true).read_all(&mut &buffer[..]),
+            Err(YpbankError::BinaryChecksumMismatch { id: 1002 })
+        );
+    }
+
+    #[test]
+    fn test_description_length() {
+        assert_eq!(BinRecord::description_length(17), Ok(17));
+        assert_eq!(
+            BinRecord::description_length((u32::MAX - BinRecord::FIXED_PART_LENGTH) as usize),
+            Ok(u32::MAX - BinRecord::FIXED_PART_LENGTH)
+        );
+        assert_eq!(
+            BinRecord::description_length(u32::MAX as usize),
+            Err(YpbankError::BinaryDescriptionTooLong)
+        );
+        assert_eq!(
+            BinRecord::description_length(usize::MAX),
+            Err(YpbankError::BinaryDescriptionTooLong)
+        );
+    }
+
+    #[test]
+    fn test_headerless_round_trip() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                None,
+            ),
+        ];
+
+        let mut buffer = vec![];
+        BinRecordWriter::new()
+            .headerless(16)
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        assert_eq!(
+            buffer.len(),
+            records.len() * (BinRecord::FIELDS_LENGTH as usize + 16)
+        );
+
+        let result = BinRecordReader::new()
+            .headerless(16)
+            .read_all(&mut Cursor::new(buffer));
+
+        assert_eq!(result, Ok(records));
+    }
+
+    #[test]
+    fn test_headerless_description_too_long() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let result = BinRecordWriter::new()
+            .headerless(8)
+            .write_all(&mut vec![], &records);
+
+        assert_eq!(result, Err(YpbankError::BinaryDescriptionTooLong));
+    }
+
+    #[test]
+    fn test_currency_versions() {
+        let record = Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )
+        .with_currency("EUR")
+        .expect("Should be valid currency");
+
+        for writer in [BinRecordWriter::new(), BinRecordWriter::new().varint()] {
+            let mut buffer = vec![];
+            writer
+                .write_all(&mut buffer, std::slice::from_ref(&record))
+                .expect("Should write successfully");
+
+            assert_eq!(buffer[BinRecord::HEADER.len()], BinRecord::VERSION);
+            assert!(buffer.windows(3).any(|code| code == b"EUR"));
+            assert_eq!(
+                BinRecordReader::new().read_all(&mut &buffer[..]),
+                Ok(vec![record.clone()])
+            );
+        }
+
+        let result = BinRecordWriter::new()
+            .headerless(32)
+            .write_all(&mut vec![], std::slice::from_ref(&record));
+        assert!(matches!(result, Err(YpbankError::WriteError(_))));
+    }
+
+    #[test]
+    fn test_reference_little_endian_and_varint() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            )
+            .with_fee(25)
+            .with_reference("RF18 5390"),
+        ];
+
+        for (writer, reader) in [
+            (
+                BinRecordWriter::new().little_endian(true),
+                BinRecordReader::new().little_endian(true),
+            ),
+            (BinRecordWriter::new().varint(), BinRecordReader::new()),
+            (
+                BinRecordWriter::with_checksums(true),
+                BinRecordReader::with_checksums(true),
+            ),
+        ] {
+            let mut buffer = vec![];
+            writer
+                .write_all(&mut buffer, &records)
+                .expect("Should write successfully");
+            assert_eq!(reader.read_all(&mut &buffer[..]), Ok(records.clone()));
+        }
+
+        let result = BinRecordWriter::new()
+            .headerless(32)
+            .write_all(&mut vec![], &records);
+        assert!(matches!(result, Err(YpbankError::WriteError(_))));
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                u64::MAX,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: u64::MAX,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                "Payment for services, invoice #123".to_string(),
+            ),
+        ];
+
+        let mut fixed = vec![];
+        BinRecordWriter::new()
+            .write_all(&mut fixed, &records)
+            .expect("Should write successfully");
+
+        let mut varint = vec![];
+        BinRecordWriter::new()
+            .varint()
+            .write_all(&mut varint, &records)
+            .expect("Should write successfully");
+
+        assert!(varint.len() < fixed.len());
+        assert_eq!(
+            BinRecordReader::new().read_all(&mut Cursor::new(varint)),
+            Ok(records)
+        );
+    }
+
+    #[test]
+    fn test_varint_encoding() {
+        let mut buffer = vec![];
+        write_varint(&mut buffer, 300);
+        assert_eq!(buffer, vec![0xac, 0x02]);
+
+        let mut bytes = &buffer[..];
+        assert_eq!(read_varint(&mut bytes), Ok(300));
+        assert!(bytes.is_empty());
+
+        let mut truncated: &[u8] = &[0x80];
+        assert_eq!(
+            read_varint(&mut truncated),
+            Err(YpbankError::BinaryRecordTooShort)
+        );
+
+        let mut overflow: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02];
+        assert_eq!(
+            read_varint(&mut overflow),
+            Err(YpbankError::BinaryUnexpectedValue)
+        );
+    }
 }
@@ -0,0 +1,586 @@
+//! Module for comparing two sets of records by id
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+use crate::{
+    Record, RecordStatus,
+    error::{Result, YpbankError},
+};
+
+/// Difference between two sets of records, all id lists are sorted
+#[derive(Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct Diff {
+    /// Ids of records present only in first set
+    pub only_in_first: Vec<u64>,
+    /// Ids of records present only in second set
+    pub only_in_second: Vec<u64>,
+    /// Ids of records present in both sets with different values
+    pub differing: Vec<u64>,
+    /// Differing fields of every record listed in `differing`
+    pub fields: BTreeMap<u64, Vec<FieldDiff>>,
+}
+
+impl Diff {
+    /// Check if both sets contain the same records
+    pub fn is_empty(&self) -> bool {
+        self.only_in_first.is_empty() && self.only_in_second.is_empty() && self.differing.is_empty()
+    }
+}
+
+/// Single field which has different values in two records with the same id
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct FieldDiff {
+    /// Name of field
+    pub field: &'static str,
+    /// Value in first record
+    pub left: String,
+    /// Value in second record
+    pub right: String,
+}
+
+/// Record field taking part in comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Amount of record
+    Amount,
+    /// Status of record
+    Status,
+    /// Timestamp of record
+    Timestamp,
+    /// Description of record
+    Description,
+    /// Type of record together with involved accounts
+    Type,
+    /// ISO 4217 currency code of record
+    Currency,
+    /// Fee charged for record
+    Fee,
+    /// Reference of record in external system
+    Reference,
+}
+
+impl Field {
+    /// Every field in order of comparison
+    pub const ALL: [Field; 8] = [
+        Field::Amount,
+        Field::Status,
+        Field::Timestamp,
+        Field::Description,
+        Field::Type,
+        Field::Currency,
+        Field::Fee,
+        Field::Reference,
+    ];
+
+    /// Name of field accepted by [`Field::from_str`]
+    pub fn option_name(&self) -> &'static str {
+        match self {
+            Field::Type => "type",
+            other => other.name(),
+        }
+    }
+
+    /// Name used in [`FieldDiff::field`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Amount => "amount",
+            Field::Status => "status",
+            Field::Timestamp => "timestamp",
+            Field::Description => "description",
+            Field::Type => "record_type",
+            Field::Currency => "currency",
+            Field::Fee => "fee",
+            Field::Reference => "reference",
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = YpbankError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_lowercase();
+        Field::ALL
+            .into_iter()
+            .find(|field| field.option_name() == name)
+            .ok_or_else(|| YpbankError::UnknownField(s.to_string()))
+    }
+}
+
+/// Serialize diff to pretty-printed JSON
+#[cfg(feature = "json")]
+pub fn diff_to_json(diff: &Diff) -> Result<String> {
+    serde_json::to_string_pretty(diff).map_err(|e| YpbankError::WriteError(e.to_string()))
+}
+
+/// List fields which differ between two records, ids are not compared and missing description equals empty one
+pub fn diff_fields(a: &Record, b: &Record) -> Vec<FieldDiff> {
+    diff_fields_ignoring(a, b, &[])
+}
+
+/// Same as [`diff_fields`] but skips `ignored` fields
+pub fn diff_fields_ignoring(a: &Record, b: &Record, ignored: &[Field]) -> Vec<FieldDiff> {
+    let fields: [(Field, String, String); 8] = [
+        (
+            Field::Amount,
+            a.amount().to_string(),
+            b.amount().to_string(),
+        ),
+        (
+            Field::Status,
+            a.status().to_string(),
+            b.status().to_string(),
+        ),
+        (
+            Field::Timestamp,
+            a.timestamp().to_string(),
+            b.timestamp().to_string(),
+        ),
+        (
+            Field::Description,
+            a.description().unwrap_or_default().to_string(),
+            b.description().unwrap_or_default().to_string(),
+        ),
+        (
+            Field::Type,
+            format!("{:?}", a.record_type()),
+            format!("{:?}", b.record_type()),
+        ),
+        (
+            Field::Currency,
+            a.currency().to_string(),
+            b.currency().to_string(),
+        ),
+        (Field::Fee, a.fee().to_string(), b.fee().to_string()),
+        (
+            Field::Reference,
+            a.reference().to_string(),
+            b.reference().to_string(),
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(field, left, right)| !ignored.contains(field) && left != right)
+        .map(|(field, left, right)| FieldDiff {
+            field: field.name(),
+            left,
+            right,
+        })
+        .collect()
+}
+
+/// Compare two sets of records matching them by id
+pub fn compare_records(a: &[Record], b: &[Record]) -> Diff {
+    compare_records_ignoring(a, b, &[])
+}
+
+/// Same as [`compare_records`] but records differing only in `ignored` fields are considered equal
+pub fn compare_records_ignoring(a: &[Record], b: &[Record], ignored: &[Field]) -> Diff {
+    let first = records_to_map(a);
+    let second = records_to_map(b);
+
+    let mut diff = Diff::default();
+
+    for (id, record) in &first {
+        match second.get(id) {
+            None => diff.only_in_first.push(*id),
+            Some(other) => {
+                let fields = diff_fields_ignoring(record, other, ignored);
+                if !fields.is_empty() {
+                    diff.differing.push(*id);
+                    diff.fields.insert(*id, fields);
+                }
+            }
+        }
+    }
+    diff.only_in_second = second
+        .keys()
+        .filter(|id| !first.contains_key(id))
+        .copied()
+        .collect();
+
+    diff.only_in_first.sort_unstable();
+    diff.only_in_second.sort_unstable();
+    diff.differing.sort_unstable();
+
+    diff
+}
+
+/// Check if status may change from `from` to `to`, only pending records may change and only to final status
+pub fn is_legal_transition(from: &RecordStatus, to: &RecordStatus) -> bool {
+    from == to || *from == RecordStatus::Pending
+}
+
+/// Ids of records whose status changed illegally between exports, with statuses before and after, sorted by id.
+///
+/// Records present only in one of the sets are skipped
+pub fn validate_transitions(
+    before: &[Record],
+    after: &[Record],
+) -> Vec<(u64, RecordStatus, RecordStatus)> {
+    let after = records_to_map(after);
+
+    let mut illegal: Vec<_> = before
+        .iter()
+        .filter_map(|record| {
+            let other = after.get(&record.id)?;
+            (!is_legal_transition(record.status(), other.status()))
+                .then(|| (record.id, record.status().clone(), other.status().clone()))
+        })
+        .collect();
+    illegal.sort_unstable_by_key(|(id, _, _)| *id);
+    illegal
+}
+
+fn records_to_map(records: &[Record]) -> HashMap<u64, &Record> {
+    HashMap::from_iter(records.iter().map(|r| (r.id, r)))
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use crate::{RecordStatus, RecordType};
+
+    use super::*;
+
+    #[allow(dead_code)]
+    fn deposit(id: u64, amount: u64) -> Record {
+        Record::new(
+            id,
+            RecordType::Deposit { to_user_id: 501 },
+            amount,
+            1672531200000,
+            RecordStatus::Success,
+            "Deposit".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_compare_identical() {
+        let a = vec![deposit(1, 100), deposit(2, 200)];
+        let b = vec![deposit(2, 200), deposit(1, 100)];
+
+        let diff = compare_records(&a, &b);
+
+        assert!(diff.is_empty());
+        assert_eq!(diff, Diff::default());
+    }
+
+    #[test]
+    fn test_compare_disjoint() {
+        let a = vec![deposit(2, 100), deposit(1, 100)];
+        let b = vec![deposit(3, 100)];
+
+        assert_eq!(
+            compare_records(&a, &b),
+            Diff {
+                only_in_first: vec![1, 2],
+                only_in_second: vec![3],
+                differing: vec![],
+                fields: BTreeMap::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_compare_different_values() {
+        let a = vec![deposit(1, 100), deposit(2, 200)];
+        let b = vec![deposit(1, 100), deposit(2, 300)];
+
+        assert_eq!(
+            compare_records(&a, &b),
+            Diff {
+                only_in_first: vec![],
+                only_in_second: vec![],
+                differing: vec![2],
+                fields: BTreeMap::from([(
+                    2,
+                    vec![FieldDiff {
+                        field: "amount",
+                        left: "200".to_string(),
+                        right: "300".to_string(),
+                    }]
+                )]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_diff_fields_amount_and_status() {
+        let a = deposit(1, 100);
+        let b = Record::new(
+            1,
+            RecordType::Deposit { to_user_id: 501 },
+            150,
+            1672531200000,
+            RecordStatus::Failure,
+            "Deposit".to_string(),
+        );
+
+        assert_eq!(
+            diff_fields(&a, &b),
+            vec![
+                FieldDiff {
+                    field: "amount",
+                    left: "100".to_string(),
+                    right: "150".to_string(),
+                },
+                FieldDiff {
+                    field: "status",
+                    left: "SUCCESS".to_string(),
+                    right: "FAILURE".to_string(),
+                },
+            ]
+        );
+        assert!(diff_fields(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_compare_ignoring_description() {
+        let a = vec![deposit(1, 100)];
+        let b = vec![Record::new(
+            1,
+            RecordType::Deposit { to_user_id: 501 },
+            100,
+            1672531200000,
+            RecordStatus::Success,
+            "Deposit via terminal".to_string(),
+        )];
+
+        assert_eq!(compare_records(&a, &b).differing, vec![1]);
+        assert!(compare_records_ignoring(&a, &b, &[Field::Description]).is_empty());
+        assert_eq!(
+            compare_records_ignoring(&a, &b, &[Field::Amount]).differing,
+            vec![1]
+        );
+        assert_eq!("type".parse::<Field>(), Ok(Field::Type));
+    }
+
+    #[test]
+    fn test_compare_different_currency() {
+        let a = vec![deposit(1, 100)];
+        let b = vec![
+            deposit(1, 100)
+                .with_currency("EUR")
+                .expect("Should be valid currency"),
+        ];
+
+        assert_eq!(
+            compare_records(&a, &b).fields,
+            BTreeMap::from([(
+                1,
+                vec![FieldDiff {
+                    field: "currency",
+                    left: "XXX".to_string(),
+                    right: "EUR".to_string(),
+                }]
+            )])
+        );
+        assert!(compare_records_ignoring(&a, &b, &[Field::Currency]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_every_field() {
+        for field in Field::ALL {
+            assert_eq!(field.option_name().parse::<Field>(), Ok(field));
+        }
+
+        let error = "balance"
+            .parse::<Field>()
+            .expect_err("Should be unknown field");
+        assert_eq!(
+            error.to_string(),
+            "Unknown field 'balance', available options are 'amount', 'status', 'timestamp', \
+'description', 'type', 'currency', 'fee' and 'reference'"
+        );
+    }
+
+    #[test]
+    fn test_diff_fields_covers_every_field() {
+        let a = deposit(1, 100);
+        // Fails to compile when record gains a field, which then has to be compared as well
+        let Record {
+            id: _,
+            record_type: _,
+            amount: _,
+            timestamp: _,
+            status: _,
+            description: _,
+            currency: _,
+            fee: _,
+            reference: _,
+        } = &a;
+
+        let changed = [
+            deposit(1, 150),
+            Record::new(
+                1,
+                RecordType::Withdrawal { from_user_id: 501 },
+                100,
+                1672531200000,
+                RecordStatus::Success,
+                "Deposit".to_string(),
+            ),
+            Record::new(
+                1,
+                RecordType::Deposit { to_user_id: 501 },
+                100,
+                1672534800000,
+                RecordStatus::Success,
+                "Deposit".to_string(),
+            ),
+            Record::new(
+                1,
+                RecordType::Deposit { to_user_id: 501 },
+                100,
+                1672531200000,
+                RecordStatus::Pending,
+                "Deposit".to_string(),
+            ),
+            Record::new(
+                1,
+                RecordType::Deposit { to_user_id: 501 },
+                100,
+                1672531200000,
+                RecordStatus::Success,
+                None,
+            ),
+            deposit(1, 100)
+                .with_currency("EUR")
+                .expect("Should be valid currency"),
+            deposit(1, 100).with_fee(5),
+            deposit(1, 100).with_reference("INV-1"),
+        ];
+        for b in changed {
+            assert_ne!(a, b);
+            assert_eq!(
+                compare_records(std::slice::from_ref(&a), std::slice::from_ref(&b)).differing,
+                vec![1],
+                "{b:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compare_reversal_refers_to_original() {
+        let reversal = Record::new(
+            3,
+            RecordType::Reversal {
+                from_user_id: 501,
+                to_user_id: 0,
+                original_tx_id: 1,
+            },
+            100,
+            1672534800000,
+            RecordStatus::Success,
+            "Chargeback".to_string(),
+        );
+        let before = vec![deposit(1, 100), deposit(2, 200)];
+        let after = vec![deposit(1, 100), deposit(2, 200), reversal];
+
+        let diff = compare_records(&before, &after);
+        assert_eq!(diff.only_in_second, vec![3]);
+
+        let originals = diff
+            .only_in_second
+            .iter()
+            .filter_map(|id| after.iter().find(|r| r.id == *id))
+            .filter_map(|r| r.record_type().original_tx_id())
+            .collect::<Vec<_>>();
+        assert_eq!(originals, vec![1]);
+        assert!(
+            originals
+                .iter()
+                .all(|id| before.iter().chain(&after).any(|r| r.id == *id))
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_diff_to_json() {
+        let a = vec![deposit(1, 100), deposit(2, 200)];
+        let b = vec![deposit(2, 300), deposit(3, 100)];
+
+        let json = diff_to_json(&compare_records(&a, &b)).expect("Should serialize diff");
+        let value: serde_json::Value = serde_json::from_str(&json).expect("Should be valid JSON");
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "only_in_first": [1],
+                "only_in_second": [3],
+                "differing": [2],
+                "fields": {
+                    "2": [{"field": "amount", "left": "200", "right": "300"}]
+                }
+            })
+        );
+    }
+
+    #[allow(dead_code)]
+    fn with_status(id: u64, status: RecordStatus) -> Record {
+        Record::new(
+            id,
+            RecordType::Deposit { to_user_id: 501 },
+            100,
+            1672531200000,
+            status,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_is_legal_transition() {
+        assert!(is_legal_transition(
+            &RecordStatus::Pending,
+            &RecordStatus::Success
+        ));
+        assert!(is_legal_transition(
+            &RecordStatus::Pending,
+            &RecordStatus::Failure
+        ));
+        assert!(is_legal_transition(
+            &RecordStatus::Success,
+            &RecordStatus::Success
+        ));
+        assert!(!is_legal_transition(
+            &RecordStatus::Success,
+            &RecordStatus::Pending
+        ));
+        assert!(!is_legal_transition(
+            &RecordStatus::Failure,
+            &RecordStatus::Success
+        ));
+    }
+
+    #[test]
+    fn test_validate_transitions() {
+        let before = vec![
+            with_status(3, RecordStatus::Success),
+            with_status(1, RecordStatus::Pending),
+            with_status(2, RecordStatus::Pending),
+            with_status(4, RecordStatus::Failure),
+            with_status(5, RecordStatus::Success),
+        ];
+        let after = vec![
+            with_status(1, RecordStatus::Success),
+            with_status(2, RecordStatus::Failure),
+            with_status(3, RecordStatus::Pending),
+            with_status(4, RecordStatus::Cancelled),
+            with_status(6, RecordStatus::Pending),
+        ];
+
+        assert_eq!(
+            validate_transitions(&before, &after),
+            vec![
+                (3, RecordStatus::Success, RecordStatus::Pending),
+                (4, RecordStatus::Failure, RecordStatus::Cancelled),
+            ]
+        );
+        assert!(validate_transitions(&before[1..3], &after).is_empty());
+    }
+}
@@ -1,45 +1,363 @@
-use std::io::{Read, Write};
+//! Module with reader and writer of YPBankCsv format
+use std::collections::HashMap;
+use std::io::{BufReader, Read, Write};
 
-use crate::{Record, RecordReader, RecordStatus, RecordType, RecordWriter, error::YpbankError};
+use crate::{
+    ReaderConfig, Record, RecordReader, RecordStatus, RecordType, RecordWriter, TimestampUnit,
+    error::{Result, YpbankError},
+};
 use serde::{Deserialize, Serialize};
 
-pub(crate) struct CsvRecordReader;
+/// Reader of CSV records, columns are matched by header
+/// ```
+/// use ypbank_converter::{CsvRecordReader, RecordReader};
+///
+/// let csv = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+/// 1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,\"Initial account funding\"
+/// ";
+///
+/// let records = CsvRecordReader::new()
+///     .read_all(&mut csv.as_bytes())
+///     .expect("Should read records");
+/// assert_eq!(records[0].amount(), 50000);
+/// ```
+#[derive(Clone)]
+pub struct CsvRecordReader {
+    delimiter: u8,
+    has_headers: bool,
+    /// Upper case source header to canonical field name
+    aliases: HashMap<String, String>,
+    config: ReaderConfig,
+}
 
 impl CsvRecordReader {
-    pub(crate) fn new() -> Self {
-        Self
+    /// Reader of comma separated records
+    pub fn new() -> Self {
+        Self {
+            delimiter: b',',
+            has_headers: true,
+            aliases: HashMap::new(),
+            config: ReaderConfig::default(),
+        }
     }
-}
 
-impl RecordReader for CsvRecordReader {
-    fn read_all<R: Read>(&self, r: &mut R) -> Result<Vec<Record>, YpbankError> {
-        let mut rdr = csv::Reader::from_reader(r);
-        rdr.deserialize::<CsvRecord>()
+    /// Reader of records separated by given delimiter, e.g. `b';'` or `b'\t'`
+    pub fn with_delimiter(delimiter: u8) -> Self {
+        Self::new().delimiter(delimiter)
+    }
+
+    /// Reader trimming values and rejecting unknown columns according to `config`
+    pub fn with_config(delimiter: u8, config: ReaderConfig) -> Self {
+        Self::with_delimiter(delimiter).config(config)
+    }
+
+    /// Reader for files without header row, columns are matched by position
+    pub fn without_headers() -> Self {
+        Self::new().has_headers(false)
+    }
+
+    /// Reader renaming source headers to canonical field names, e.g. `VALUE` to `AMOUNT`
+    pub fn with_aliases(aliases: HashMap<String, String>) -> Self {
+        Self::new().aliases(aliases)
+    }
+
+    /// Read records separated by given delimiter, e.g. `b';'` or `b'\t'`
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Trim values and reject unknown columns according to `config`
+    pub fn config(mut self, config: ReaderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Match columns by header row if `has_headers` is set, otherwise by position
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Rename source headers to canonical field names, e.g. `VALUE` to `AMOUNT`
+    pub fn aliases(mut self, aliases: HashMap<String, String>) -> Self {
+        self.aliases = aliases
+            .into_iter()
+            .map(|(source, canonical)| (source.to_uppercase(), canonical.to_uppercase()))
+            .collect();
+        self
+    }
+
+    fn csv_reader<R: Read>(&self, r: R) -> csv::Reader<BufReader<R>> {
+        let mut r = BufReader::new(r);
+        crate::skip_bom(&mut r);
+
+        csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .flexible(true)
+            .trim(if self.config.trim_whitespace {
+                csv::Trim::All
+            } else {
+                csv::Trim::None
+            })
+            .from_reader(r)
+    }
+
+    fn headers<R: Read>(
+        &self,
+        rdr: &mut csv::Reader<BufReader<R>>,
+    ) -> Result<Option<csv::StringRecord>> {
+        if self.has_headers {
+            // Upstream systems capitalize headers inconsistently, fields are matched in upper case
+            let headers: csv::StringRecord = rdr
+                .headers()?
+                .iter()
+                .map(|header| {
+                    let header = header.to_uppercase();
+                    self.aliases.get(&header).cloned().unwrap_or(header)
+                })
+                .collect();
+
+            // Empty input has no header row at all
+            if !headers.is_empty()
+                && let Some(missing) = CsvRecord::HEADERS[..CsvRecord::REQUIRED_COLUMNS]
+                    .iter()
+                    .find(|column| !headers.iter().any(|header| header == **column))
+            {
+                return Err(YpbankError::CsvMissingColumn(missing.to_string()));
+            }
+
+            if self.config.strict
+                && let Some(unknown) = headers
+                    .iter()
+                    .find(|header| !CsvRecord::HEADERS.contains(header))
+            {
+                return Err(YpbankError::CsvUnknownColumn(unknown.to_string()));
+            }
+
+            Ok(Some(headers))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Read all parsable records, rows that failed are returned with their index instead of aborting
+    pub fn read_all_lenient<R: Read>(&self, r: &mut R) -> (Vec<Record>, Vec<(usize, YpbankError)>) {
+        let mut records = vec![];
+        let mut failures = vec![];
+        for (index, (_, result)) in self.read_all_by_line(r).into_iter().enumerate() {
+            match result {
+                Ok(record) => records.push(record),
+                Err(e) => failures.push((index, e)),
+            }
+        }
+
+        (records, failures)
+    }
+
+    /// Parse every row independently pairing result with 1-based line number of row, header error is reported at line 1
+    pub(crate) fn read_all_by_line<R: Read>(&self, r: &mut R) -> Vec<(u64, Result<Record>)> {
+        let mut rdr = self.csv_reader(r);
+        let headers = match self.headers(&mut rdr) {
+            Ok(headers) => headers,
+            Err(e) => return vec![(1, Err(e))],
+        };
+
+        rdr.records()
             .map(|res| {
-                res.map_err(YpbankError::from)
-                    .and_then(|csv_record| csv_record.try_into())
+                let position = match &res {
+                    Ok(row) => row.position(),
+                    Err(e) => e.position(),
+                };
+                let line = position.map_or(0, |p| p.line());
+                (line, self.parse_row(res, headers.as_ref()))
             })
-            .collect::<Result<Vec<Record>, YpbankError>>()
+            .collect()
+    }
+
+    /// Convert single CSV row to record, rows are matched by position if there are no headers
+    fn parse_row(
+        &self,
+        res: Result<csv::StringRecord, csv::Error>,
+        headers: Option<&csv::StringRecord>,
+    ) -> Result<Record> {
+        let row = res?;
+        let line = row.position().map_or(0, |p| p.line());
+        let well_formed = match headers {
+            Some(headers) => row.len() == headers.len(),
+            None => (CsvRecord::LEGACY_COLUMNS..=CsvRecord::COLUMNS).contains(&row.len()),
+        };
+        if !well_formed {
+            return Err(YpbankError::CsvMalformedRow {
+                row: line,
+                raw: row
+                    .iter()
+                    .collect::<Vec<_>>()
+                    .join(&char::from(self.delimiter).to_string()),
+            });
+        }
+        row.deserialize::<CsvRecord>(headers)?
+            .into_record(line, &self.config)
     }
 }
 
-pub(crate) struct CsvRecordWriter;
+impl Default for CsvRecordReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordReader for CsvRecordReader {
+    fn read_all<R: Read>(&self, r: &mut R) -> Result<Vec<Record>> {
+        let mut rdr = self.csv_reader(r);
+        let headers = self.headers(&mut rdr)?;
+        rdr.records()
+            .map(|res| self.parse_row(res, headers.as_ref()))
+            .collect::<Result<Vec<Record>>>()
+    }
+
+    fn records<'a>(&self, r: &'a mut dyn Read) -> Box<dyn Iterator<Item = Result<Record>> + 'a> {
+        let mut rdr = self.csv_reader(r);
+        let headers = match self.headers(&mut rdr) {
+            Ok(headers) => headers,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+        let reader = self.clone();
+
+        Box::new(
+            rdr.into_records()
+                .map(move |res| reader.parse_row(res, headers.as_ref()))
+                .scan(false, |failed, res| {
+                    if *failed {
+                        return None;
+                    }
+                    *failed = res.is_err();
+                    Some(res)
+                }),
+        )
+    }
+}
+
+/// Writer of CSV records with header row
+pub struct CsvRecordWriter {
+    delimiter: u8,
+    /// Indices of written columns in [`CsvRecord::HEADERS`], all columns are written if `None`
+    columns: Option<Vec<usize>>,
+    timestamp_unit: TimestampUnit,
+    /// Write timestamps as RFC 3339 strings instead of epoch values
+    #[cfg(feature = "timestamp")]
+    iso_timestamps: bool,
+}
 
 impl CsvRecordWriter {
-    pub(crate) fn new() -> Self {
-        Self
+    /// Writer of comma separated records
+    pub fn new() -> Self {
+        Self {
+            delimiter: b',',
+            columns: None,
+            timestamp_unit: TimestampUnit::Millis,
+            #[cfg(feature = "timestamp")]
+            iso_timestamps: false,
+        }
+    }
+
+    /// Writer of records separated by given delimiter
+    pub fn with_delimiter(delimiter: u8) -> Self {
+        Self::new().delimiter(delimiter)
+    }
+
+    /// Writer of comma separated records with timestamps in given unit
+    pub fn with_timestamp_unit(timestamp_unit: TimestampUnit) -> Self {
+        Self::new().timestamp_unit(timestamp_unit)
+    }
+
+    /// Writer of given columns only, in given order
+    pub fn with_columns(columns: &[&str]) -> Result<Self> {
+        Self::new().columns(columns)
+    }
+
+    /// Separate records by given delimiter
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Write timestamps as epoch values in given unit
+    pub fn timestamp_unit(mut self, timestamp_unit: TimestampUnit) -> Self {
+        self.timestamp_unit = timestamp_unit;
+        self
+    }
+
+    /// Write given columns only, in given order
+    pub fn columns(mut self, columns: &[&str]) -> Result<Self> {
+        let columns = columns
+            .iter()
+            .map(|column| {
+                CsvRecord::HEADERS
+                    .iter()
+                    .position(|header| header == column)
+                    .ok_or_else(|| YpbankError::CsvUnknownColumn(column.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.columns = Some(columns);
+        Ok(self)
+    }
+
+    /// Write timestamps as RFC 3339 strings, e.g. `2023-01-01T00:00:00.000Z`, instead of epoch values
+    #[cfg(feature = "timestamp")]
+    pub fn iso_timestamps(mut self, enabled: bool) -> Self {
+        self.iso_timestamps = enabled;
+        self
+    }
+
+    /// Timestamp as it is written to file
+    fn format_timestamp(&self, millis: u64) -> String {
+        #[cfg(feature = "timestamp")]
+        if self.iso_timestamps
+            && let Some(formatted) = crate::timestamp::format_rfc3339(millis)
+        {
+            return formatted;
+        }
+
+        self.timestamp_unit.from_millis(millis).to_string()
+    }
+}
+
+impl Default for CsvRecordWriter {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl RecordWriter for CsvRecordWriter {
-    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<(), YpbankError> {
-        let mut writer = csv::Writer::from_writer(w);
+    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<()> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .quote_style(csv::QuoteStyle::Always)
+            .from_writer(w);
 
-        for record in records {
-            let csv_record = CsvRecord::from(record);
-            if let Err(e) = writer.serialize(csv_record) {
-                return Err(YpbankError::WriteError(e.to_string()));
+        let to_csv_record = |record: &Record| CsvRecord {
+            timestamp: self.format_timestamp(record.timestamp),
+            ..CsvRecord::from(record)
+        };
+        let result = match &self.columns {
+            None => records
+                .iter()
+                .try_for_each(|record| writer.serialize(to_csv_record(record))),
+            Some(columns) => {
+                let header = columns.iter().map(|&i| CsvRecord::HEADERS[i]);
+                writer.write_record(header).and_then(|_| {
+                    records.iter().try_for_each(|record| {
+                        let values = to_csv_record(record).values();
+                        writer.write_record(columns.iter().map(|&i| &values[i]))
+                    })
+                })
             }
+        };
+        if let Err(e) = result {
+            return Err(YpbankError::WriteError(e.to_string()));
         }
 
         if let Err(e) = writer.flush() {
@@ -62,18 +380,78 @@ struct CsvRecord {
     to_user_id: u64,
     #[serde(rename = "AMOUNT")]
     amount: u64,
+    /// Epoch value or, with `timestamp` feature, RFC 3339 string
     #[serde(rename = "TIMESTAMP")]
-    timestamp: u64,
+    timestamp: String,
     #[serde(rename = "STATUS")]
     status: String,
     #[serde(rename = "DESCRIPTION")]
-    description: String,
+    description: Option<String>,
+    #[serde(rename = "ORIGINAL_TX_ID", default)]
+    original_tx_id: Option<u64>,
+    #[serde(rename = "CURRENCY", default)]
+    currency: Option<String>,
+    #[serde(rename = "FEE", default)]
+    fee: Option<u64>,
+    #[serde(rename = "REFERENCE", default)]
+    reference: Option<String>,
 }
 
-impl TryInto<Record> for CsvRecord {
-    type Error = YpbankError;
+impl CsvRecord {
+    /// Number of columns in CSV row
+    const COLUMNS: usize = 12;
+
+    /// Column names in order they are written
+    const HEADERS: [&str; CsvRecord::COLUMNS] = [
+        "TX_ID",
+        "TX_TYPE",
+        "FROM_USER_ID",
+        "TO_USER_ID",
+        "AMOUNT",
+        "TIMESTAMP",
+        "STATUS",
+        "DESCRIPTION",
+        "ORIGINAL_TX_ID",
+        "CURRENCY",
+        "FEE",
+        "REFERENCE",
+    ];
+
+    /// Field values in [`CsvRecord::HEADERS`] order
+    fn values(&self) -> [String; CsvRecord::COLUMNS] {
+        [
+            self.id.to_string(),
+            self.record_type.clone(),
+            self.from_user_id.to_string(),
+            self.to_user_id.to_string(),
+            self.amount.to_string(),
+            self.timestamp.clone(),
+            self.status.clone(),
+            self.description.clone().unwrap_or_default(),
+            self.original_tx_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            self.currency.clone().unwrap_or_default(),
+            self.fee.map(|fee| fee.to_string()).unwrap_or_default(),
+            self.reference.clone().unwrap_or_default(),
+        ]
+    }
+
+    /// Number of columns in files written before `ORIGINAL_TX_ID` column was added
+    const LEGACY_COLUMNS: usize = 8;
+
+    /// Number of leading columns in [`CsvRecord::HEADERS`] every file must have
+    const REQUIRED_COLUMNS: usize = 7;
+
+    /// Convert to record, `row` is line number reported in errors about unexpected values.
+    ///
+    /// Numeric timestamp and unused user id are handled according to `config`
+    fn into_record(self, row: u64, config: &ReaderConfig) -> Result<Record> {
+        let unexpected = |value: &str| YpbankError::CsvUnexpectedValue {
+            row,
+            value: value.to_string(),
+        };
 
-    fn try_into(self) -> Result<Record, Self::Error> {
         let record_type = match self.record_type.as_str() {
             "DEPOSIT" => Ok(RecordType::Deposit {
                 to_user_id: self.to_user_id,
@@ -85,51 +463,77 @@ impl TryInto<Record> for CsvRecord {
                 from_user_id: self.from_user_id,
                 to_user_id: self.to_user_id,
             }),
-            other => Err(YpbankError::CsvUnexpectedValue(other.to_string())),
+            "FEE" => Ok(RecordType::Fee {
+                from_user_id: self.from_user_id,
+            }),
+            "REVERSAL" => Ok(RecordType::Reversal {
+                from_user_id: self.from_user_id,
+                to_user_id: self.to_user_id,
+                original_tx_id: self.original_tx_id.ok_or_else(|| {
+                    YpbankError::InvalidRecord(format!(
+                        "reversal {} has no original transaction id",
+                        self.id
+                    ))
+                })?,
+            }),
+            other => Err(unexpected(other)),
         }?;
+        if config.reject_unused_user_ids {
+            record_type.check_unused_user_id(self.id, self.from_user_id, self.to_user_id)?;
+        }
 
         let status = match self.status.as_str() {
             "SUCCESS" => Ok(RecordStatus::Success),
             "PENDING" => Ok(RecordStatus::Pending),
             "FAILURE" => Ok(RecordStatus::Failure),
-            other => Err(YpbankError::CsvUnexpectedValue(other.to_string())),
+            "CANCELLED" => Ok(RecordStatus::Cancelled),
+            other => Err(unexpected(other)),
         }?;
 
-        Ok(Record::new(
+        let timestamp = crate::parse_timestamp(&self.timestamp, config.timestamp_unit)
+            .ok_or_else(|| unexpected(&self.timestamp))?;
+
+        Record::new(
             self.id,
             record_type,
             self.amount,
-            self.timestamp,
+            timestamp,
             status,
             self.description,
-        ))
+        )
+        // Files written before `CURRENCY`, `FEE` and `REFERENCE` columns were added have none of them
+        .with_currency(self.currency.as_deref().unwrap_or(Record::DEFAULT_CURRENCY))
+        .map(|record| {
+            record
+                .with_fee(self.fee.unwrap_or_default())
+                .with_reference(self.reference.unwrap_or_default())
+        })
     }
 }
 
 impl From<&Record> for CsvRecord {
     fn from(value: &Record) -> Self {
-        let (record_type, from_user_id, to_user_id) = match value.record_type {
-            RecordType::Deposit { to_user_id } => ("DEPOSIT".to_string(), 0, to_user_id),
-            RecordType::Withdrawal { from_user_id } => ("WITHDRAWAL".to_string(), from_user_id, 0),
-            RecordType::Transfer {
-                from_user_id,
-                to_user_id,
-            } => ("TRANSFER".to_string(), from_user_id, to_user_id),
-        };
+        let record_type = value.record_type.to_string();
+        let (from_user_id, to_user_id) = value.record_type.endpoints();
         Self {
             id: value.id,
             record_type,
             from_user_id,
             to_user_id,
             amount: value.amount,
-            timestamp: value.timestamp,
+            timestamp: value.timestamp.to_string(),
             status: match value.status {
                 RecordStatus::Success => "SUCCESS",
                 RecordStatus::Failure => "FAILURE",
                 RecordStatus::Pending => "PENDING",
+                RecordStatus::Cancelled => "CANCELLED",
             }
             .to_string(),
-            description: value.description.to_string(),
+            description: value.description.clone(),
+            original_tx_id: value.record_type.original_tx_id(),
+            currency: Some(value.currency().to_string()),
+            fee: Some(value.fee),
+            reference: Some(value.reference.clone()),
         }
     }
 }
@@ -150,12 +554,16 @@ mod tests {
             from_user_id: 0,
             to_user_id: 501,
             amount: 50000,
-            timestamp: 1672531200000,
+            timestamp: "1672531200000".to_string(),
             status: "SUCCESS".to_string(),
-            description: "Initial account funding".to_string(),
+            description: Some("Initial account funding".to_string()),
+            original_tx_id: None,
+            currency: None,
+            fee: None,
+            reference: None,
         };
         assert_eq!(
-            deposit.try_into(),
+            deposit.into_record(1, &ReaderConfig::default()),
             Ok(Record::new(
                 1001,
                 RecordType::Deposit { to_user_id: 501 },
@@ -175,12 +583,16 @@ mod tests {
             from_user_id: 501,
             to_user_id: 502,
             amount: 15000,
-            timestamp: 1672534800000,
+            timestamp: "1672534800000".to_string(),
             status: "FAILURE".to_string(),
-            description: "Payment for services, invoice #123".to_string(),
+            description: Some("Payment for services, invoice #123".to_string()),
+            original_tx_id: None,
+            currency: None,
+            fee: None,
+            reference: None,
         };
         assert_eq!(
-            withdrawal.try_into(),
+            withdrawal.into_record(1, &ReaderConfig::default()),
             Ok(Record::new(
                 1002,
                 RecordType::Transfer {
@@ -203,12 +615,16 @@ mod tests {
             from_user_id: 502,
             to_user_id: 0,
             amount: 1000,
-            timestamp: 1672538400000,
+            timestamp: "1672538400000".to_string(),
             status: "PENDING".to_string(),
-            description: "ATM withdrawal".to_string(),
+            description: Some("ATM withdrawal".to_string()),
+            original_tx_id: None,
+            currency: None,
+            fee: None,
+            reference: None,
         };
         assert_eq!(
-            withdrawal.try_into(),
+            withdrawal.into_record(1, &ReaderConfig::default()),
             Ok(Record::new(
                 1003,
                 RecordType::Withdrawal { from_user_id: 502 },
@@ -228,15 +644,22 @@ mod tests {
             from_user_id: 502,
             to_user_id: 0,
             amount: 1000,
-            timestamp: 1672538400000,
+            timestamp: "1672538400000".to_string(),
             status: "PENDING".to_string(),
-            description: "ATM withdrawal".to_string(),
+            description: Some("ATM withdrawal".to_string()),
+            original_tx_id: None,
+            currency: None,
+            fee: None,
+            reference: None,
         };
 
-        let result: Result<Record, YpbankError> = withdrawal.try_into();
+        let result: Result<Record> = withdrawal.into_record(1, &ReaderConfig::default());
         assert_eq!(
             result,
-            Err(YpbankError::CsvUnexpectedValue("something".to_string()))
+            Err(YpbankError::CsvUnexpectedValue {
+                row: 1,
+                value: "something".to_string()
+            })
         )
     }
 
@@ -248,15 +671,22 @@ mod tests {
             from_user_id: 502,
             to_user_id: 0,
             amount: 1000,
-            timestamp: 1672538400000,
+            timestamp: "1672538400000".to_string(),
             status: "INITIAL".to_string(),
-            description: "ATM withdrawal".to_string(),
+            description: Some("ATM withdrawal".to_string()),
+            original_tx_id: None,
+            currency: None,
+            fee: None,
+            reference: None,
         };
 
-        let result: Result<Record, YpbankError> = withdrawal.try_into();
+        let result: Result<Record> = withdrawal.into_record(1, &ReaderConfig::default());
         assert_eq!(
             result,
-            Err(YpbankError::CsvUnexpectedValue("INITIAL".to_string()))
+            Err(YpbankError::CsvUnexpectedValue {
+                row: 1,
+                value: "INITIAL".to_string()
+            })
         )
     }
 
@@ -350,11 +780,440 @@ mod tests {
 
         assert_eq!(
             String::from_utf8(writer.into_inner()).expect("Should be correct string"),
-            r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
-1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,Initial account funding
-1002,TRANSFER,501,502,15000,1672534800000,FAILURE,"Payment for services, invoice #123"
-1003,WITHDRAWAL,502,0,1000,1672538400000,PENDING,ATM withdrawal
+            r#""TX_ID","TX_TYPE","FROM_USER_ID","TO_USER_ID","AMOUNT","TIMESTAMP","STATUS","DESCRIPTION","ORIGINAL_TX_ID","CURRENCY","FEE","REFERENCE"
+"1001","DEPOSIT","0","501","50000","1672531200000","SUCCESS","Initial account funding","","XXX","0",""
+"1002","TRANSFER","501","502","15000","1672534800000","FAILURE","Payment for services, invoice #123","","XXX","0",""
+"1003","WITHDRAWAL","502","0","1000","1672538400000","PENDING","ATM withdrawal","","XXX","0",""
 "#
         )
     }
+
+    #[test]
+    fn test_write_all_error_carries_message() {
+        struct BrokenPipeWriter;
+
+        impl Write for BrokenPipeWriter {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "pipe is closed",
+                ))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let result = CsvRecordWriter::new().write_all(&mut BrokenPipeWriter, &records);
+
+        match result {
+            Err(YpbankError::WriteError(reason)) => assert!(reason.contains("pipe is closed")),
+            other => panic!("Expected WriteError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_write_with_columns() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Withdrawal { from_user_id: 501 },
+                1000,
+                1672534800000,
+                RecordStatus::Pending,
+                None,
+            ),
+        ];
+
+        let mut buffer = vec![];
+        CsvRecordWriter::with_columns(&["TX_ID", "AMOUNT"])
+            .expect("Columns should be known")
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        assert_eq!(
+            String::from_utf8(buffer).expect("Should be correct string"),
+            "\"TX_ID\",\"AMOUNT\"\n\"1001\",\"50000\"\n\"1002\",\"1000\"\n"
+        );
+        assert!(matches!(
+            CsvRecordWriter::with_columns(&["TX_ID", "BALANCE"]),
+            Err(YpbankError::CsvUnknownColumn(column)) if column == "BALANCE"
+        ));
+    }
+
+    #[test]
+    fn test_read_all_mixed_case_headers() {
+        let csv_data = r#"Tx_Id,tx_type,From_User_Id,TO_USER_ID,amount,Timestamp,STATUS,Description
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1003,WITHDRAWAL,502,0,1000,1672538400000,PENDING,"ATM withdrawal""#;
+
+        let records = CsvRecordReader::new().read_all(&mut Cursor::new(csv_data));
+
+        assert_eq!(
+            records,
+            Ok(vec![
+                Record::new(
+                    1001,
+                    RecordType::Deposit { to_user_id: 501 },
+                    50000,
+                    1672531200000,
+                    RecordStatus::Success,
+                    "Initial account funding".to_string(),
+                ),
+                Record::new(
+                    1003,
+                    RecordType::Withdrawal { from_user_id: 502 },
+                    1000,
+                    1672538400000,
+                    RecordStatus::Pending,
+                    "ATM withdrawal".to_string(),
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_read_all_strict_and_lenient() {
+        let csv_data = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,CHANNEL
+1001, DEPOSIT,0,501,50000 ,1672531200000,SUCCESS,ATM
+";
+
+        let strict = CsvRecordReader::with_config(b',', ReaderConfig::strict())
+            .read_all(&mut Cursor::new(csv_data));
+        assert_eq!(
+            strict,
+            Err(YpbankError::CsvUnknownColumn("CHANNEL".to_string()))
+        );
+
+        let lenient = CsvRecordReader::with_config(b',', ReaderConfig::lenient())
+            .read_all(&mut Cursor::new(csv_data));
+        assert_eq!(
+            lenient,
+            Ok(vec![Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                None,
+            )])
+        );
+    }
+
+    #[test]
+    fn test_read_all_with_bom() {
+        let csv_data =
+            "\u{feff}TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,\"\u{feff}Funding\"
+";
+
+        let records = CsvRecordReader::with_config(b',', ReaderConfig::strict())
+            .read_all(&mut Cursor::new(csv_data));
+
+        assert_eq!(
+            records,
+            Ok(vec![Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "\u{feff}Funding".to_string(),
+            )])
+        );
+    }
+
+    #[test]
+    fn test_read_all_with_aliases() {
+        let csv_data = r#"ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,Value,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding""#;
+        let aliases = HashMap::from([
+            ("ID".to_string(), "TX_ID".to_string()),
+            ("VALUE".to_string(), "AMOUNT".to_string()),
+        ]);
+
+        let records = CsvRecordReader::with_aliases(aliases).read_all(&mut Cursor::new(csv_data));
+
+        assert_eq!(
+            records,
+            Ok(vec![Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            )])
+        );
+
+        let records = CsvRecordReader::new().read_all(&mut Cursor::new(csv_data));
+        assert_eq!(
+            records,
+            Err(YpbankError::CsvMissingColumn("TX_ID".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_read_all_reports_row_of_bad_status() {
+        let csv_data = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1002,TRANSFER,501,502,15000,1672534800000,INITIAL,"Payment for services""#;
+
+        let error = CsvRecordReader::new()
+            .read_all(&mut Cursor::new(csv_data))
+            .expect_err("Should fail on unknown status");
+
+        assert_eq!(
+            error,
+            YpbankError::CsvUnexpectedValue {
+                row: 3,
+                value: "INITIAL".to_string()
+            }
+        );
+        assert_eq!(error.to_string(), "Csv row 3: unexpected value INITIAL");
+    }
+
+    #[test]
+    fn test_read_all_unquoted_delimiter() {
+        let csv_data = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1002,TRANSFER,501,502,15000,1672534800000,FAILURE,Payment for services, invoice #123"#;
+
+        let records = CsvRecordReader::new().read_all(&mut Cursor::new(csv_data));
+
+        assert_eq!(
+            records,
+            Err(YpbankError::CsvMalformedRow {
+                row: 3,
+                raw: "1002,TRANSFER,501,502,15000,1672534800000,FAILURE,Payment for services, invoice #123"
+                    .to_string()
+            })
+        )
+    }
+
+    #[test]
+    fn test_write_all_quotes_plain_description() {
+        let records = vec![Record::new(
+            1003,
+            RecordType::Withdrawal { from_user_id: 502 },
+            1000,
+            1672538400000,
+            RecordStatus::Pending,
+            "ATM".to_string(),
+        )];
+
+        let mut buffer: Vec<u8> = Vec::new();
+        CsvRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let output = String::from_utf8(buffer).expect("Should be correct string");
+        assert!(
+            output
+                .lines()
+                .nth(1)
+                .is_some_and(|l| l.ends_with(r#","ATM","","XXX","0","""#))
+        );
+
+        let result = CsvRecordReader::new().read_all(&mut Cursor::new(output));
+        assert_eq!(result, Ok(records));
+    }
+
+    #[test]
+    fn test_read_all_lenient() {
+        let csv_data = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1002,TRANSFER,501,502,15000,1672534800000,UNKNOWN,"Payment for services, invoice #123"
+1003,WITHDRAWAL,502,0,1000,1672538400000,PENDING,"ATM withdrawal""#;
+
+        let (records, failures) =
+            CsvRecordReader::new().read_all_lenient(&mut Cursor::new(csv_data));
+
+        assert_eq!(
+            records,
+            vec![
+                Record::new(
+                    1001,
+                    RecordType::Deposit { to_user_id: 501 },
+                    50000,
+                    1672531200000,
+                    RecordStatus::Success,
+                    "Initial account funding".to_string(),
+                ),
+                Record::new(
+                    1003,
+                    RecordType::Withdrawal { from_user_id: 502 },
+                    1000,
+                    1672538400000,
+                    RecordStatus::Pending,
+                    "ATM withdrawal".to_string(),
+                ),
+            ]
+        );
+        assert_eq!(
+            failures,
+            vec![(
+                1,
+                YpbankError::CsvUnexpectedValue {
+                    row: 3,
+                    value: "UNKNOWN".to_string()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_semicolon_delimiter() {
+        let csv_data = r#"TX_ID;TX_TYPE;FROM_USER_ID;TO_USER_ID;AMOUNT;TIMESTAMP;STATUS;DESCRIPTION
+1002;TRANSFER;501;502;15000;1672534800000;FAILURE;"Payment for services; invoice #123"
+1003;WITHDRAWAL;502;0;1000;1672538400000;PENDING;ATM withdrawal, cash"#;
+
+        let records = CsvRecordReader::with_delimiter(b';').read_all(&mut Cursor::new(csv_data));
+
+        let expected = vec![
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                "Payment for services; invoice #123".to_string(),
+            ),
+            Record::new(
+                1003,
+                RecordType::Withdrawal { from_user_id: 502 },
+                1000,
+                1672538400000,
+                RecordStatus::Pending,
+                "ATM withdrawal, cash".to_string(),
+            ),
+        ];
+        assert_eq!(records, Ok(expected.clone()));
+
+        let mut buffer: Vec<u8> = Vec::new();
+        CsvRecordWriter::with_delimiter(b';')
+            .write_all(&mut buffer, &expected)
+            .expect("Should write successfully");
+
+        assert_eq!(
+            String::from_utf8(buffer).expect("Should be correct string"),
+            r#""TX_ID";"TX_TYPE";"FROM_USER_ID";"TO_USER_ID";"AMOUNT";"TIMESTAMP";"STATUS";"DESCRIPTION";"ORIGINAL_TX_ID";"CURRENCY";"FEE";"REFERENCE"
+"1002";"TRANSFER";"501";"502";"15000";"1672534800000";"FAILURE";"Payment for services; invoice #123";"";"XXX";"0";""
+"1003";"WITHDRAWAL";"502";"0";"1000";"1672538400000";"PENDING";"ATM withdrawal, cash";"";"XXX";"0";""
+"#
+        );
+    }
+
+    #[test]
+    fn test_read_all_without_headers() {
+        let csv_data = r#"1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1002,TRANSFER,501,502,15000,1672534800000,FAILURE,"Payment for services, invoice #123"
+1003,WITHDRAWAL,502,0,1000,1672538400000,PENDING,"ATM withdrawal""#;
+        let with_headers = format!(
+            "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION\n{csv_data}"
+        );
+
+        let records = CsvRecordReader::without_headers().read_all(&mut Cursor::new(csv_data));
+
+        assert!(records.as_ref().is_ok_and(|r| r.len() == 3));
+        assert_eq!(
+            records,
+            CsvRecordReader::new().read_all(&mut Cursor::new(with_headers))
+        );
+    }
+
+    #[test]
+    fn test_read_all_without_headers_column_mismatch() {
+        let csv_data = "1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS";
+
+        let records = CsvRecordReader::without_headers().read_all(&mut Cursor::new(csv_data));
+
+        assert_eq!(
+            records,
+            Err(YpbankError::CsvMalformedRow {
+                row: 1,
+                raw: csv_data.to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_seconds_timestamp_unit() {
+        let csv_data = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200,SUCCESS,"Initial account funding"
+"#;
+        let config = ReaderConfig {
+            timestamp_unit: TimestampUnit::Seconds,
+            ..ReaderConfig::lenient()
+        };
+
+        let records = CsvRecordReader::with_config(b',', config)
+            .read_all(&mut Cursor::new(csv_data))
+            .expect("Should read successfully");
+        assert_eq!(records[0].timestamp(), 1672531200000);
+
+        let mut buffer = vec![];
+        CsvRecordWriter::with_timestamp_unit(TimestampUnit::Seconds)
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+        let written = String::from_utf8(buffer).expect("Should be correct string");
+        assert!(written.contains(r#""1672531200","SUCCESS""#));
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn test_iso_timestamps() {
+        let csv_data = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1002,DEPOSIT,0,501,50000,2023-01-01T03:00:00+03:00,SUCCESS,"Initial account funding"
+"#;
+
+        let records = CsvRecordReader::new()
+            .read_all(&mut Cursor::new(csv_data))
+            .expect("Should read successfully");
+        assert_eq!(records[0].timestamp(), 1672531200000);
+        assert_eq!(records[1].timestamp(), 1672531200000);
+
+        let mut buffer = vec![];
+        CsvRecordWriter::new()
+            .iso_timestamps(true)
+            .write_all(&mut buffer, &records[..1])
+            .expect("Should write successfully");
+        let written = String::from_utf8(buffer).expect("Should be correct string");
+        assert!(written.contains(r#""2023-01-01T00:00:00.000Z","SUCCESS""#));
+        assert_eq!(
+            CsvRecordReader::new().read_all(&mut Cursor::new(written)),
+            Ok(records[..1].to_vec())
+        );
+
+        let invalid = csv_data.replace("2023-01-01T03:00:00+03:00", "yesterday");
+        assert_eq!(
+            CsvRecordReader::new().read_all(&mut Cursor::new(invalid)),
+            Err(YpbankError::CsvUnexpectedValue {
+                row: 3,
+                value: "yesterday".to_string()
+            })
+        );
+    }
 }
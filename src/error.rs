@@ -1,37 +1,180 @@
 //! Module containig list of possible errors
 use std::{error::Error, fmt::Display};
 
-/// List of possible errors
+use crate::{FileFormat, compare::Field};
+
+/// Underlying error kept for [`Error::source`], errors are compared by message
+#[derive(Debug)]
+pub struct ErrorSource(Box<dyn Error + Send + Sync + 'static>);
+
+impl ErrorSource {
+    /// Wrap original error
+    pub fn new(error: impl Error + Send + Sync + 'static) -> Self {
+        ErrorSource(Box::new(error))
+    }
+}
+
+impl Display for ErrorSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for ErrorSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for ErrorSource {}
+
+/// Result type used across the crate
+pub type Result<T, E = YpbankError> = std::result::Result<T, E>;
+
+/// List of possible errors.
+///
+/// New variants may be added in minor releases, so matches need a wildcard arm
+/// ```
+/// use ypbank_converter::error::YpbankError;
+///
+/// fn describe(error: &YpbankError) -> &'static str {
+///     match error {
+///         YpbankError::FileOpenError(_) => "file is missing",
+///         YpbankError::CsvMalformedRow { .. } => "check CSV quoting",
+///         _ => "unexpected error",
+///     }
+/// }
+///
+/// assert_eq!(describe(&YpbankError::NoInputs), "unexpected error");
+/// ```
 #[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum YpbankError {
     /// Unable to  open file
     FileOpenError(String),
     /// Given file format is not known to library
     UnknownFormat(String),
+    /// Format of input can't be detected from its content
+    UndetectedFormat,
+    /// Functionality is not compiled in, holds name of cargo feature enabling it
+    FeatureDisabled(&'static str),
+    /// Command line argument has invalid form
+    InvalidArgument(String),
+    /// Format can only be written
+    ReadNotSupported(FileFormat),
+    /// Format produces binary data which can't be represented as string
+    NotTextFormat(FileFormat),
     /// Error parsing CSV file
-    CsvParseError(String),
+    CsvParseError {
+        /// Line number of row, 0 if error is not tied to a row
+        row: u64,
+        /// Underlying CSV error
+        source: ErrorSource,
+    },
     /// Unexpected value in CSV file
-    CsvUnexpectedValue(String),
+    CsvUnexpectedValue {
+        /// Line number of row
+        row: u64,
+        /// Unexpected value
+        value: String,
+    },
+    /// CSV row has wrong number of columns, usually caused by unquoted delimiter
+    CsvMalformedRow {
+        /// Line number of row
+        row: u64,
+        /// Row content
+        raw: String,
+    },
+    /// Required column is absent in CSV header
+    CsvMissingColumn(String),
+    /// Requested CSV column is not a known field
+    CsvUnknownColumn(String),
     /// Text field not found in text record
     TextFieldNotFound(String),
     /// Text field has incorrect value
     TextUnexpectedFieldValue(String, String),
-    /// Unable to parse text field value
-    TextUnableToParse(String),
+    /// Unable to parse text line
+    TextUnableToParse {
+        /// 1-based line number
+        line_no: usize,
+        /// Line content
+        content: String,
+    },
     /// Text record contains duplicate entries
-    TextDuplicateField(String),
+    TextDuplicateField {
+        /// 1-based line number of repeated field
+        line_no: usize,
+        /// Field name
+        field: String,
+    },
+    /// Text record contains field unknown to format, reported in strict mode only
+    TextUnknownField {
+        /// 1-based line number of field
+        line_no: usize,
+        /// Field name
+        field: String,
+    },
     /// Unbale to read text data
-    TextReadError(String),
+    TextReadError {
+        /// 1-based line number
+        line_no: usize,
+        /// Underlying read error
+        source: ErrorSource,
+    },
     /// Got unexpected value while reading binary data
     BinaryUnexpectedValue,
     /// Read error while reading binary data
-    BinaryReadError(String),
+    BinaryReadError(ErrorSource),
     /// Description of binary record is too long and does not fit into record data size
     BinaryDescriptionTooLong,
+    /// Bytes after last complete binary record do not form record header
+    BinaryTrailingData,
     /// Binary record does not contain enough data
     BinaryRecordTooShort,
+    /// Declared binary record length differs from number of bytes its fields occupy
+    BinaryLengthMismatch(u32, u32),
+    /// Binary record is written by newer version of format
+    UnsupportedBinaryVersion(u8),
+    /// Checksum of binary record doesn't match its content
+    BinaryChecksumMismatch {
+        /// Id of corrupted record
+        id: u64,
+    },
+    /// Error reading input
+    ReadError(String),
     /// Error writing file
     WriteError(String),
+    /// Record is semantically invalid
+    InvalidRecord(String),
+    /// Unable to parse relative duration
+    InvalidDuration(String),
+    /// Unknown key for sorting records
+    UnknownSortKey(String),
+    /// Unknown record field name
+    UnknownField(String),
+    /// Lower bound of timestamp range is after upper bound
+    InvalidRange {
+        /// Lower bound
+        from: u64,
+        /// Upper bound
+        to: u64,
+    },
+    /// Histogram bucket size is zero
+    ZeroBucketSize,
+    /// No input sources were given
+    NoInputs,
+    /// Records with the same id have different content
+    ConflictingDuplicate {
+        /// Id shared by conflicting records
+        id: u64,
+    },
+    /// Record read back after writing differs from written one
+    RoundTripMismatch {
+        /// Id of the first differing record
+        id: u64,
+    },
+    /// Generic IO error not tied to particular format
+    Io(ErrorSource),
 }
 
 impl Display for YpbankError {
@@ -40,20 +183,56 @@ impl Display for YpbankError {
             YpbankError::FileOpenError(reason) => write!(f, "Error while opening file: {reason}"),
             YpbankError::UnknownFormat(format) => write!(
                 f,
-                "Unknown file format '{format}', available options are 'binary', 'csv' and 'text'"
+                "Unknown file format '{format}', available options are 'binary', 'csv', 'text', 'tsv' and 'markdown'"
+            ),
+            YpbankError::UndetectedFormat => {
+                write!(f, "Unable to detect input format, specify it explicitly")
+            }
+            YpbankError::FeatureDisabled(feature) => {
+                write!(
+                    f,
+                    "This build does not support {feature}, enable `{feature}` feature"
+                )
+            }
+            YpbankError::InvalidArgument(reason) => write!(f, "Invalid argument: {reason}"),
+            YpbankError::ReadNotSupported(format) => {
+                write!(f, "Reading {format} format is not supported")
+            }
+            YpbankError::NotTextFormat(format) => {
+                write!(f, "{format} format is not a text format")
+            }
+            YpbankError::CsvParseError { row, source } => {
+                write!(f, "Parsing CSV error, row {row}: {source}")
+            }
+            YpbankError::CsvUnexpectedValue { row, value } => {
+                write!(f, "Csv row {row}: unexpected value {value}")
+            }
+            YpbankError::CsvMalformedRow { row, raw } => write!(
+                f,
+                "Csv row {row} has unexpected number of columns, check that values containing delimiter are quoted: {raw}"
             ),
-            YpbankError::CsvParseError(error) => write!(f, "Parsing CSV error: {error}"),
-            YpbankError::CsvUnexpectedValue(value) => write!(f, "Csv unexpected value: {value}"),
+            YpbankError::CsvMissingColumn(column) => {
+                write!(f, "Required CSV column is missing: {column}")
+            }
+            YpbankError::CsvUnknownColumn(column) => write!(f, "Unknown CSV column: {column}"),
             YpbankError::TextFieldNotFound(field) => write!(f, "Text field not found: {field}"),
             YpbankError::TextUnexpectedFieldValue(field, value) => {
                 write!(f, "Text field {field} unexpected value: {value}")
             }
-            YpbankError::TextUnableToParse(line) => write!(f, "Unable to parse txt line: {line}"),
-            YpbankError::TextDuplicateField(field) => {
-                write!(f, "Text duplicate field found: {field}")
+            YpbankError::TextUnableToParse { line_no, content } => {
+                write!(f, "Unable to parse txt line {line_no}: {content}")
             }
-            YpbankError::TextReadError(reason) => {
-                write!(f, "Error while reading text file: {reason}")
+            YpbankError::TextDuplicateField { line_no, field } => {
+                write!(f, "Text duplicate field found on line {line_no}: {field}")
+            }
+            YpbankError::TextUnknownField { line_no, field } => {
+                write!(f, "Text unknown field found on line {line_no}: {field}")
+            }
+            YpbankError::TextReadError { line_no, source } => {
+                write!(
+                    f,
+                    "Error while reading text file on line {line_no}: {source}"
+                )
             }
             YpbankError::BinaryUnexpectedValue => {
                 write!(f, "Unable to read binary format, unexpected value")
@@ -64,23 +243,128 @@ impl Display for YpbankError {
             YpbankError::BinaryDescriptionTooLong => {
                 write!(f, "Binary description length exceeds record length")
             }
+            YpbankError::BinaryTrailingData => {
+                write!(f, "Unexpected trailing data after last binary record")
+            }
             YpbankError::BinaryRecordTooShort => {
                 write!(
                     f,
                     "Binary record is too shord and does not contain all required fields"
                 )
             }
+            YpbankError::BinaryLengthMismatch(declared, actual) => {
+                write!(
+                    f,
+                    "Binary record length mismatch: declared {declared} bytes, fields occupy {actual} bytes"
+                )
+            }
+            YpbankError::UnsupportedBinaryVersion(version) => {
+                write!(f, "Unsupported binary format version {version}")
+            }
+            YpbankError::BinaryChecksumMismatch { id } => {
+                write!(f, "Binary record {id} is corrupted, checksum mismatch")
+            }
+            YpbankError::ReadError(reason) => write!(f, "Unable to read input: {reason}"),
             YpbankError::WriteError(reason) => {
                 write!(f, "Unable to write output: {reason}")
             }
+            YpbankError::InvalidRecord(reason) => write!(f, "Invalid record: {reason}"),
+            YpbankError::InvalidDuration(duration) => write!(
+                f,
+                "Invalid duration '{duration}', expected number followed by 'd', 'h', 'm' or 's'"
+            ),
+            YpbankError::UnknownSortKey(key) => write!(
+                f,
+                "Unknown sort key '{key}', available options are 'id', 'timestamp' and 'amount'"
+            ),
+            YpbankError::UnknownField(field) => {
+                let options = Field::ALL.map(|option| format!("'{}'", option.option_name()));
+                let (last, rest) = options.split_last().expect("Field has variants");
+                write!(
+                    f,
+                    "Unknown field '{field}', available options are {} and {last}",
+                    rest.join(", ")
+                )
+            }
+            YpbankError::InvalidRange { from, to } => {
+                write!(f, "Invalid timestamp range: from {from} is after to {to}")
+            }
+            YpbankError::ZeroBucketSize => write!(f, "Bucket size must be greater than zero"),
+            YpbankError::NoInputs => write!(f, "No inputs given, at least one is required"),
+            YpbankError::ConflictingDuplicate { id } => {
+                write!(f, "Records with id {id} have different content")
+            }
+            YpbankError::RoundTripMismatch { id } => {
+                write!(f, "Record {id} changed after writing and reading it back")
+            }
+            YpbankError::Io(reason) => write!(f, "IO error: {reason}"),
         }
     }
 }
 
-impl Error for YpbankError {}
+impl Error for YpbankError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            YpbankError::CsvParseError { source, .. }
+            | YpbankError::BinaryReadError(source)
+            | YpbankError::TextReadError { source, .. }
+            | YpbankError::Io(source) => Some(source.0.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<csv::Error> for YpbankError {
     fn from(value: csv::Error) -> Self {
-        YpbankError::CsvParseError(value.to_string())
+        YpbankError::CsvParseError {
+            row: value.position().map_or(0, |p| p.line()),
+            source: ErrorSource::new(value),
+        }
+    }
+}
+
+impl From<std::io::Error> for YpbankError {
+    fn from(value: std::io::Error) -> Self {
+        YpbankError::Io(ErrorSource::new(value))
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_from_io_error() {
+        let error: YpbankError =
+            std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pipe closed").into();
+
+        assert!(matches!(error, YpbankError::Io(_)));
+        assert_eq!(error.to_string(), "IO error: pipe closed");
+        assert_eq!(
+            error.source().map(|source| source.to_string()),
+            Some("pipe closed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_csv_error_source() {
+        let mut reader = csv::Reader::from_reader("A,B\n1,2,3\n".as_bytes());
+        let error: YpbankError = reader
+            .records()
+            .next()
+            .expect("Should have row")
+            .expect_err("Should fail on extra column")
+            .into();
+
+        assert!(matches!(error, YpbankError::CsvParseError { row: 2, .. }));
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn test_feature_disabled_names_feature() {
+        assert_eq!(
+            YpbankError::FeatureDisabled("gzip").to_string(),
+            "This build does not support gzip, enable `gzip` feature"
+        );
     }
 }
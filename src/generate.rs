@@ -0,0 +1,125 @@
+//! Module generating sample records for fixtures and benchmarks
+use crate::{Record, RecordStatus, RecordType};
+
+/// Small deterministic pseudo-random generator (SplitMix64), good enough for sample data
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Value in `0..bound`
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+/// Generate `count` valid records of every type and status, the same `seed` always gives the same records.
+///
+/// Ids are sequential starting from 1, timestamps grow from 2023-01-01
+pub fn generate_records(count: usize, seed: u64) -> Vec<Record> {
+    const START_TIMESTAMP: u64 = 1672531200000;
+    const USERS: u64 = 1000;
+    const STATUSES: [RecordStatus; 4] = [
+        RecordStatus::Success,
+        RecordStatus::Failure,
+        RecordStatus::Pending,
+        RecordStatus::Cancelled,
+    ];
+    const DESCRIPTIONS: [&str; 6] = [
+        "Salary",
+        "ATM withdrawal",
+        "Payment for services, invoice #123",
+        "Monthly maintenance fee",
+        "Chargeback",
+        "Transfer to \"savings\"",
+    ];
+
+    let mut rng = SplitMix64(seed);
+    let mut timestamp = START_TIMESTAMP;
+
+    (1..=count as u64)
+        .map(|id| {
+            let from_user_id = rng.below(USERS) + 1;
+            // Offset in 1..USERS never maps receiver back to sender
+            let to_user_id = (from_user_id + rng.below(USERS - 1)) % USERS + 1;
+            let record_type = match rng.below(5) {
+                0 => RecordType::Deposit { to_user_id },
+                1 => RecordType::Withdrawal { from_user_id },
+                2 => RecordType::Fee { from_user_id },
+                3 if id > 1 => RecordType::Reversal {
+                    from_user_id,
+                    to_user_id,
+                    original_tx_id: rng.below(id - 1) + 1,
+                },
+                _ => RecordType::Transfer {
+                    from_user_id,
+                    to_user_id,
+                },
+            };
+            timestamp += rng.below(3_600_000);
+            let description = (rng.below(4) > 0).then(|| rng.pick(&DESCRIPTIONS).to_string());
+
+            Record::new(
+                id,
+                record_type,
+                rng.below(1_000_000) + 1,
+                timestamp,
+                rng.pick(&STATUSES).clone(),
+                description,
+            )
+        })
+        .collect()
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use crate::RecordKind;
+
+    use super::*;
+
+    #[test]
+    fn test_generate_records_is_deterministic() {
+        let records = generate_records(200, 42);
+
+        assert_eq!(records.len(), 200);
+        assert_eq!(records, generate_records(200, 42));
+        assert_ne!(records, generate_records(200, 43));
+        assert!(generate_records(0, 42).is_empty());
+    }
+
+    #[test]
+    fn test_generate_records_are_valid_and_varied() {
+        let records = generate_records(500, 7);
+
+        assert!(records.iter().all(|r| r.validate().is_ok()));
+        assert!(
+            records
+                .windows(2)
+                .all(|w| w[0].timestamp() <= w[1].timestamp())
+        );
+        for kind in [
+            RecordKind::Deposit,
+            RecordKind::Withdrawal,
+            RecordKind::Transfer,
+            RecordKind::Fee,
+            RecordKind::Reversal,
+        ] {
+            assert!(records.iter().any(|r| r.record_type().kind() == kind));
+        }
+        assert!(
+            records
+                .iter()
+                .any(|r| *r.status() == RecordStatus::Cancelled)
+        );
+    }
+}
@@ -4,25 +4,43 @@
 #![warn(missing_docs)]
 
 use std::{
+    collections::HashMap,
     fmt::Display,
-    io::{Read, Write},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Cursor, Read, Write},
+    path::Path,
     str::FromStr,
 };
 
-use crate::{
+pub use crate::{
     bin_format::{BinRecordReader, BinRecordWriter},
     csv_format::{CsvRecordReader, CsvRecordWriter},
-    error::YpbankError,
+    markdown_format::MarkdownRecordWriter,
     txt_format::{TextRecordReader, TextRecordWriter},
 };
+use crate::{
+    error::{Result, YpbankError},
+    set::RecordSet,
+};
 
-mod bin_format;
-mod csv_format;
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod bin_format;
+pub mod compare;
+pub mod csv_format;
 pub mod error;
-mod txt_format;
+#[cfg(feature = "gen")]
+pub mod generate;
+pub mod markdown_format;
+pub mod relative_time;
+pub mod set;
+pub mod stats;
+#[cfg(feature = "timestamp")]
+pub mod timestamp;
+pub mod txt_format;
 
 /// Available file formats
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileFormat {
     /// Binary format for effective storage usage
     Binary,
@@ -32,6 +50,46 @@ pub enum FileFormat {
 
     /// Human-readable text format
     Text,
+
+    /// CSV table with tab delimiter
+    Tsv,
+
+    /// Markdown table for reports, supports only writing
+    Markdown,
+}
+
+impl FileFormat {
+    /// All file formats supporting both reading and writing
+    pub fn all() -> [FileFormat; 4] {
+        [
+            FileFormat::Binary,
+            FileFormat::Csv,
+            FileFormat::Text,
+            FileFormat::Tsv,
+        ]
+    }
+
+    /// Detect file format by path extension: `.bin`, `.csv`, `.txt`, `.tsv` or `.md`, trailing `.gz` or `.zst` is skipped
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<FileFormat> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.to_lowercase())
+            .unwrap_or_default();
+        let name = name
+            .strip_suffix(".gz")
+            .or_else(|| name.strip_suffix(".zst"))
+            .unwrap_or(&name);
+        match Path::new(name).extension().and_then(|e| e.to_str()) {
+            Some("bin") => Ok(FileFormat::Binary),
+            Some("csv") => Ok(FileFormat::Csv),
+            Some("txt") => Ok(FileFormat::Text),
+            Some("tsv") => Ok(FileFormat::Tsv),
+            Some("md") => Ok(FileFormat::Markdown),
+            _ => Err(YpbankError::UnknownFormat(path.display().to_string())),
+        }
+    }
 }
 
 impl Display for FileFormat {
@@ -43,6 +101,8 @@ impl Display for FileFormat {
                 FileFormat::Binary => "Binary",
                 FileFormat::Csv => "Csv",
                 FileFormat::Text => "Text",
+                FileFormat::Tsv => "Tsv",
+                FileFormat::Markdown => "Markdown",
             }
         )
     }
@@ -56,13 +116,15 @@ impl FromStr for FileFormat {
             "binary" => Ok(FileFormat::Binary),
             "csv" => Ok(FileFormat::Csv),
             "text" => Ok(FileFormat::Text),
+            "tsv" => Ok(FileFormat::Tsv),
+            "markdown" => Ok(FileFormat::Markdown),
             _ => Err(YpbankError::UnknownFormat(s.to_string())),
         }
     }
 }
 
 /// Format-independent Record structure
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Record {
     /// Id of record
     pub id: u64,
@@ -70,18 +132,25 @@ pub struct Record {
     amount: u64,
     timestamp: u64,
     status: RecordStatus,
-    description: String,
+    description: Option<String>,
+    /// ISO 4217 code, always three ASCII upper case letters
+    currency: [u8; 3],
+    fee: u64,
+    reference: String,
 }
 
 impl Record {
-    /// Create new record
+    /// Currency of records read from files written before currency was added, ISO 4217 code for no currency
+    pub const DEFAULT_CURRENCY: &str = "XXX";
+
+    /// Create new record in [`Record::DEFAULT_CURRENCY`], description may be given as `String` or `Option<String>`
     pub fn new(
         id: u64,
         record_type: RecordType,
         amount: u64,
         timestamp: u64,
         status: RecordStatus,
-        description: String,
+        description: impl Into<Option<String>>,
     ) -> Self {
         Self {
             id,
@@ -89,13 +158,287 @@ impl Record {
             amount,
             timestamp,
             status,
-            description,
+            description: description.into(),
+            currency: *b"XXX",
+            fee: 0,
+            reference: String::new(),
+        }
+    }
+
+    /// Type of record together with involved accounts
+    pub fn record_type(&self) -> &RecordType {
+        &self.record_type
+    }
+
+    /// Amount of money in record
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    /// Amount as decimal string with `scale` digits after the point, e.g. `50000` with scale 2 is `500.00`
+    pub fn amount_decimal(&self, scale: u32) -> String {
+        let scale = scale as usize;
+        if scale == 0 {
+            return self.amount.to_string();
+        }
+
+        let digits = format!("{:0width$}", self.amount, width = scale + 1);
+        let (units, fraction) = digits.split_at(digits.len() - scale);
+        format!("{units}.{fraction}")
+    }
+
+    /// Timestamp of record in milliseconds
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Timestamp of record as UTC date and time, `None` if it is out of supported range
+    #[cfg(feature = "timestamp")]
+    pub fn datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        timestamp::to_datetime(self.timestamp)
+    }
+
+    /// Timestamp of record formatted as RFC 3339 string
+    #[cfg(feature = "timestamp")]
+    pub fn rfc3339(&self) -> Option<String> {
+        timestamp::format_rfc3339(self.timestamp)
+    }
+
+    /// Copy of record with timestamp parsed from RFC 3339 string
+    #[cfg(feature = "timestamp")]
+    pub fn with_rfc3339(mut self, value: &str) -> Result<Self> {
+        self.timestamp = timestamp::parse_rfc3339(value).ok_or_else(|| {
+            YpbankError::InvalidRecord(format!("invalid RFC 3339 timestamp {value}"))
+        })?;
+        Ok(self)
+    }
+
+    /// ISO 4217 currency code of amount, e.g. `EUR`
+    pub fn currency(&self) -> &str {
+        // Only ASCII letters are ever stored
+        std::str::from_utf8(&self.currency).unwrap_or(Self::DEFAULT_CURRENCY)
+    }
+
+    /// Copy of record in given currency, fails unless code is three ASCII upper case letters
+    pub fn with_currency(mut self, currency: &str) -> Result<Self> {
+        self.currency = currency
+            .as_bytes()
+            .try_into()
+            .ok()
+            .filter(|code: &[u8; 3]| code.iter().all(u8::is_ascii_uppercase))
+            .ok_or_else(|| {
+                YpbankError::InvalidRecord(format!("invalid currency code {currency}"))
+            })?;
+        Ok(self)
+    }
+
+    /// Fee charged to payer on top of amount, zero if record has no fee
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+
+    /// Copy of record with given fee
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// External reference attached by bank, e.g. payment reference, empty if record has none
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    /// Copy of record with given reference
+    pub fn with_reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = reference.into();
+        self
+    }
+
+    /// Status of record
+    pub fn status(&self) -> &RecordStatus {
+        &self.status
+    }
+
+    /// Description of record, `None` if record has no description at all.
+    ///
+    /// Binary and CSV formats can't tell empty description from absent one and read both as `None`
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Signed amount applied to each account involved in the record.
+    ///
+    /// Amounts above `i64::MAX` are saturated
+    pub fn net_effect(&self) -> Vec<(u64, i64)> {
+        let amount = i64::try_from(self.amount).unwrap_or(i64::MAX);
+        match self.record_type {
+            RecordType::Deposit { to_user_id } => vec![(to_user_id, amount)],
+            RecordType::Withdrawal { from_user_id } | RecordType::Fee { from_user_id } => {
+                vec![(from_user_id, -amount)]
+            }
+            RecordType::Transfer {
+                from_user_id,
+                to_user_id,
+            }
+            | RecordType::Reversal {
+                from_user_id,
+                to_user_id,
+                ..
+            } => vec![(from_user_id, -amount), (to_user_id, amount)],
+        }
+    }
+
+    /// Exact balance change of every involved user with fee charged to [`RecordType::primary_user`], status is not considered.
+    ///
+    /// Unlike [`Record::net_effect`] amounts are never saturated
+    pub fn balance_changes(&self) -> Vec<(u64, i128)> {
+        let mut changes: Vec<(u64, i128)> = self
+            .net_effect()
+            .into_iter()
+            .map(|(user_id, change)| (user_id, change.signum() as i128 * self.amount as i128))
+            .collect();
+        if self.fee > 0 {
+            changes.push((self.record_type.primary_user(), -(self.fee as i128)));
+        }
+        changes
+    }
+
+    /// Check record for semantic consistency
+    pub fn validate(&self) -> Result<()> {
+        if let RecordType::Transfer {
+            from_user_id,
+            to_user_id,
+        } = self.record_type
+            && from_user_id == to_user_id
+        {
+            return Err(YpbankError::InvalidRecord(format!(
+                "transfer {} has the same sender and receiver {from_user_id}",
+                self.id
+            )));
+        }
+        if self.amount == 0 {
+            return Err(YpbankError::InvalidRecord(format!(
+                "record {} has zero amount",
+                self.id
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Start building record with named setters, see [`RecordBuilder`]
+    pub fn builder() -> RecordBuilder {
+        RecordBuilder::default()
+    }
+}
+
+/// Builder of [`Record`] validating it on [`RecordBuilder::build`]
+/// ```
+/// use ypbank_converter::{Record, RecordStatus, RecordType};
+///
+/// let record = Record::builder()
+///     .id(1001)
+///     .record_type(RecordType::Deposit { to_user_id: 501 })
+///     .amount(50000)
+///     .timestamp(1672531200000)
+///     .status(RecordStatus::Success)
+///     .description("Initial account funding")
+///     .build()
+///     .expect("Record should be valid");
+/// assert_eq!(record.amount(), 50000);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RecordBuilder {
+    id: Option<u64>,
+    record_type: Option<RecordType>,
+    amount: Option<u64>,
+    timestamp: Option<u64>,
+    status: Option<RecordStatus>,
+    description: Option<String>,
+    currency: Option<String>,
+    fee: u64,
+    reference: String,
+}
+
+impl RecordBuilder {
+    /// Set id of record
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Set type of record together with involved accounts
+    pub fn record_type(mut self, record_type: RecordType) -> Self {
+        self.record_type = Some(record_type);
+        self
+    }
+
+    /// Set amount of money
+    pub fn amount(mut self, amount: u64) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Set timestamp in milliseconds
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Set status of record
+    pub fn status(mut self, status: RecordStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Set description, record has no description if it is not set
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set ISO 4217 currency code, [`Record::DEFAULT_CURRENCY`] is used if it is not set
+    pub fn currency(mut self, currency: impl Into<String>) -> Self {
+        self.currency = Some(currency.into());
+        self
+    }
+
+    /// Set fee charged to payer, record has zero fee if it is not set
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Set external reference, record has empty reference if it is not set
+    pub fn reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = reference.into();
+        self
+    }
+
+    /// Build record, fails if any of id, type, amount, timestamp and status is not set or record is invalid
+    pub fn build(self) -> Result<Record> {
+        fn required<T>(value: Option<T>, field: &str) -> Result<T> {
+            value.ok_or_else(|| YpbankError::InvalidRecord(format!("{field} is not set")))
         }
+
+        let record = Record::new(
+            required(self.id, "id")?,
+            required(self.record_type, "record type")?,
+            required(self.amount, "amount")?,
+            required(self.timestamp, "timestamp")?,
+            required(self.status, "status")?,
+            self.description,
+        )
+        .with_currency(self.currency.as_deref().unwrap_or(Record::DEFAULT_CURRENCY))?
+        .with_fee(self.fee)
+        .with_reference(self.reference);
+        record.validate()?;
+        Ok(record)
     }
 }
 
 /// Supported record types
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RecordType {
     /// Deposit money to some account
     Deposit {
@@ -114,10 +457,194 @@ pub enum RecordType {
         /// Id of user account for money deposit
         to_user_id: u64,
     },
+    /// Fee charged by bank from some account
+    Fee {
+        /// Id of user account fee is charged from
+        from_user_id: u64,
+    },
+    /// Reversal of earlier transaction, e.g. chargeback
+    Reversal {
+        /// Id of user account money is returned from
+        from_user_id: u64,
+        /// Id of user account money is returned to
+        to_user_id: u64,
+        /// Id of reversed transaction
+        original_tx_id: u64,
+    },
+}
+
+impl RecordType {
+    /// Account most naturally owning the record, used for per-user grouping.
+    ///
+    /// Deposit is owned by the receiving account, withdrawal and fee by the account money is taken from
+    /// and transfer or reversal by the sender since it is the one who initiates the operation.
+    pub fn primary_user(&self) -> u64 {
+        match self {
+            RecordType::Deposit { to_user_id } => *to_user_id,
+            RecordType::Withdrawal { from_user_id } | RecordType::Fee { from_user_id } => {
+                *from_user_id
+            }
+            RecordType::Transfer { from_user_id, .. }
+            | RecordType::Reversal { from_user_id, .. } => *from_user_id,
+        }
+    }
+
+    /// All accounts involved in the record
+    pub fn user_ids(&self) -> Vec<u64> {
+        match self {
+            RecordType::Deposit { to_user_id } => vec![*to_user_id],
+            RecordType::Withdrawal { from_user_id } | RecordType::Fee { from_user_id } => {
+                vec![*from_user_id]
+            }
+            RecordType::Transfer {
+                from_user_id,
+                to_user_id,
+            }
+            | RecordType::Reversal {
+                from_user_id,
+                to_user_id,
+                ..
+            } => vec![*from_user_id, *to_user_id],
+        }
+    }
+
+    /// Sending and receiving accounts of the record, missing side is 0
+    pub(crate) fn endpoints(&self) -> (u64, u64) {
+        match *self {
+            RecordType::Deposit { to_user_id } => (0, to_user_id),
+            RecordType::Withdrawal { from_user_id } | RecordType::Fee { from_user_id } => {
+                (from_user_id, 0)
+            }
+            RecordType::Transfer {
+                from_user_id,
+                to_user_id,
+            }
+            | RecordType::Reversal {
+                from_user_id,
+                to_user_id,
+                ..
+            } => (from_user_id, to_user_id),
+        }
+    }
+
+    /// Mutable references to all accounts involved in the record
+    fn user_ids_mut(&mut self) -> Vec<&mut u64> {
+        match self {
+            RecordType::Deposit { to_user_id } => vec![to_user_id],
+            RecordType::Withdrawal { from_user_id } | RecordType::Fee { from_user_id } => {
+                vec![from_user_id]
+            }
+            RecordType::Transfer {
+                from_user_id,
+                to_user_id,
+            }
+            | RecordType::Reversal {
+                from_user_id,
+                to_user_id,
+                ..
+            } => vec![from_user_id, to_user_id],
+        }
+    }
+
+    /// Kind of record type without account ids
+    pub fn kind(&self) -> RecordKind {
+        match self {
+            RecordType::Deposit { .. } => RecordKind::Deposit,
+            RecordType::Withdrawal { .. } => RecordKind::Withdrawal,
+            RecordType::Transfer { .. } => RecordKind::Transfer,
+            RecordType::Fee { .. } => RecordKind::Fee,
+            RecordType::Reversal { .. } => RecordKind::Reversal,
+        }
+    }
+
+    /// Id of reversed transaction, only reversals have it
+    pub fn original_tx_id(&self) -> Option<u64> {
+        match self {
+            RecordType::Reversal { original_tx_id, .. } => Some(*original_tx_id),
+            _ => None,
+        }
+    }
+
+    /// Check that user id not used by record type is zero.
+    ///
+    /// Record type doesn't hold unused id, so formats check it while parsing if [`ReaderConfig::reject_unused_user_ids`] is set
+    pub(crate) fn check_unused_user_id(
+        &self,
+        id: u64,
+        from_user_id: u64,
+        to_user_id: u64,
+    ) -> Result<()> {
+        match self {
+            RecordType::Deposit { .. } if from_user_id != 0 => Err(YpbankError::InvalidRecord(
+                format!("deposit {id} has nonzero sender {from_user_id}"),
+            )),
+            RecordType::Withdrawal { .. } | RecordType::Fee { .. } if to_user_id != 0 => {
+                Err(YpbankError::InvalidRecord(format!(
+                    "{} {id} has nonzero receiver {to_user_id}",
+                    self.to_string().to_lowercase()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for RecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.kind().fmt(f)
+    }
+}
+
+/// Kind of record without involved accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RecordKind {
+    /// See [`RecordType::Deposit`]
+    Deposit,
+    /// See [`RecordType::Withdrawal`]
+    Withdrawal,
+    /// See [`RecordType::Transfer`]
+    Transfer,
+    /// See [`RecordType::Fee`]
+    Fee,
+    /// See [`RecordType::Reversal`]
+    Reversal,
+}
+
+impl Display for RecordKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RecordKind::Deposit => "DEPOSIT",
+                RecordKind::Withdrawal => "WITHDRAWAL",
+                RecordKind::Transfer => "TRANSFER",
+                RecordKind::Fee => "FEE",
+                RecordKind::Reversal => "REVERSAL",
+            }
+        )
+    }
+}
+
+impl FromStr for RecordKind {
+    type Err = YpbankError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "DEPOSIT" => Ok(RecordKind::Deposit),
+            "WITHDRAWAL" => Ok(RecordKind::Withdrawal),
+            "TRANSFER" => Ok(RecordKind::Transfer),
+            "FEE" => Ok(RecordKind::Fee),
+            "REVERSAL" => Ok(RecordKind::Reversal),
+            _ => Err(YpbankError::InvalidRecord(format!(
+                "unknown record type {s}"
+            ))),
+        }
+    }
 }
 
 /// Status of record
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum RecordStatus {
     /// Successfull operation
     Success,
@@ -125,29 +652,297 @@ pub enum RecordStatus {
     Failure,
     /// Pending operation
     Pending,
+    /// Operation cancelled before completion
+    Cancelled,
+}
+
+impl Display for RecordStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RecordStatus::Success => "SUCCESS",
+                RecordStatus::Failure => "FAILURE",
+                RecordStatus::Pending => "PENDING",
+                RecordStatus::Cancelled => "CANCELLED",
+            }
+        )
+    }
+}
+
+impl FromStr for RecordStatus {
+    type Err = YpbankError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SUCCESS" => Ok(RecordStatus::Success),
+            "FAILURE" => Ok(RecordStatus::Failure),
+            "PENDING" => Ok(RecordStatus::Pending),
+            "CANCELLED" => Ok(RecordStatus::Cancelled),
+            _ => Err(YpbankError::InvalidRecord(format!("unknown status {s}"))),
+        }
+    }
 }
 
 /// Trait for reading some format to unified records list
-trait RecordReader {
+pub trait RecordReader {
     /// Read all records from given reader
-    fn read_all<R: Read>(&self, r: &mut R) -> Result<Vec<Record>, YpbankError>;
+    fn read_all<R: Read>(&self, r: &mut R) -> Result<Vec<Record>>;
+
+    /// Iterate over records one by one, iteration stops after first error.
+    ///
+    /// Default implementation reads all records at once, formats override it to keep memory bounded
+    fn records<'a>(
+        &self,
+        mut r: &'a mut dyn Read,
+    ) -> Box<dyn Iterator<Item = Result<Record>> + 'a> {
+        match self.read_all(&mut r) {
+            Ok(records) => Box::new(records.into_iter().map(Ok)),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        }
+    }
 }
 
 /// Trait for writing some format from unified records list
-trait RecordWriter {
+pub trait RecordWriter {
     /// Write all records to privided writer
-    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<(), YpbankError>;
+    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<()>;
 }
 
-/// Read all records in given format from reader
-pub fn read_all_records<R: Read>(
-    reader: &mut R,
-    input_format: FileFormat,
-) -> Result<Vec<Record>, YpbankError> {
+/// Options controlling how strictly readers follow format.
+///
+/// Default options ignore unknown fields, keep whitespace and accept unused user ids,
+/// see [`ReaderConfig::strict`] and [`ReaderConfig::lenient`] for presets
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReaderConfig {
+    /// Reject unknown text fields, unknown CSV columns and trailing binary bytes instead of ignoring them
+    pub strict: bool,
+    /// Trim leading and trailing whitespace around text fields and CSV values
+    pub trim_whitespace: bool,
+    /// Unit of numeric text and CSV timestamps, RFC 3339 timestamps and binary records are not affected
+    pub timestamp_unit: TimestampUnit,
+    /// Reject deposits with sender and withdrawals or fees with receiver, otherwise unused user id is dropped
+    pub reject_unused_user_ids: bool,
+}
+
+impl ReaderConfig {
+    /// Any deviation from format is an error
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            trim_whitespace: false,
+            timestamp_unit: TimestampUnit::Millis,
+            reject_unused_user_ids: true,
+        }
+    }
+
+    /// Whitespace is trimmed, unknown fields and trailing bytes are ignored
+    pub fn lenient() -> Self {
+        Self {
+            strict: false,
+            trim_whitespace: true,
+            timestamp_unit: TimestampUnit::Millis,
+            reject_unused_user_ids: false,
+        }
+    }
+}
+
+/// Unit of epoch timestamps in files, records always keep milliseconds
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampUnit {
+    /// Seconds since epoch
+    Seconds,
+    /// Milliseconds since epoch
+    #[default]
+    Millis,
+}
+
+impl TimestampUnit {
+    /// Convert timestamp in this unit to milliseconds, `None` on overflow
+    pub fn to_millis(self, value: u64) -> Option<u64> {
+        match self {
+            TimestampUnit::Seconds => value.checked_mul(1000),
+            TimestampUnit::Millis => Some(value),
+        }
+    }
+
+    /// Convert milliseconds to this unit, fraction of second is truncated
+    pub fn from_millis(self, millis: u64) -> u64 {
+        match self {
+            TimestampUnit::Seconds => millis / 1000,
+            TimestampUnit::Millis => millis,
+        }
+    }
+}
+
+/// Read all records in given format from reader.
+///
+/// Records are only parsed, not validated, see [`read_all_records_validated`]
+pub fn read_all_records<R: Read>(reader: &mut R, input_format: FileFormat) -> Result<Vec<Record>> {
     match input_format {
         FileFormat::Binary => BinRecordReader::new().read_all(reader),
         FileFormat::Csv => CsvRecordReader::new().read_all(reader),
         FileFormat::Text => TextRecordReader::new().read_all(reader),
+        FileFormat::Tsv => CsvRecordReader::with_delimiter(b'\t').read_all(reader),
+        FileFormat::Markdown => Err(YpbankError::ReadNotSupported(FileFormat::Markdown)),
+    }
+}
+
+/// Read all records in given format using given reader options
+pub fn read_all_records_with_config<R: Read>(
+    reader: &mut R,
+    input_format: FileFormat,
+    config: ReaderConfig,
+) -> Result<Vec<Record>> {
+    match input_format {
+        FileFormat::Binary => BinRecordReader::with_config(config).read_all(reader),
+        FileFormat::Csv => CsvRecordReader::with_config(b',', config).read_all(reader),
+        FileFormat::Text => TextRecordReader::with_config(config).read_all(reader),
+        FileFormat::Tsv => CsvRecordReader::with_config(b'\t', config).read_all(reader),
+        FileFormat::Markdown => Err(YpbankError::ReadNotSupported(FileFormat::Markdown)),
+    }
+}
+
+/// Count records in given format.
+///
+/// Binary records are skipped by their declared length without decoding fields, other formats are fully parsed
+pub fn count_records<R: Read>(reader: &mut R, format: FileFormat) -> Result<usize> {
+    match format {
+        FileFormat::Binary => BinRecordReader::new().count(reader),
+        format => read_all_records(reader, format).map(|records| records.len()),
+    }
+}
+
+/// Read all records in given format into [`RecordSet`]
+pub fn read_record_set<R: Read>(reader: &mut R, input_format: FileFormat) -> Result<RecordSet> {
+    read_all_records(reader, input_format).map(RecordSet::from)
+}
+
+/// Write all records in text format with timestamps formatted as RFC 3339 strings
+#[cfg(feature = "timestamp")]
+pub fn write_all_text_records_human_time<W: Write>(
+    writer: &mut W,
+    records: &[Record],
+) -> Result<()> {
+    write_buffered(writer, |w| {
+        TextRecordWriter::with_human_time().write_all(w, records)
+    })
+}
+
+/// Check if path has `.gz` extension
+pub fn is_gzip_path<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("gz"))
+}
+
+/// Read all records in given format from gzip compressed reader
+#[cfg(feature = "gzip")]
+pub fn read_all_records_gz<R: Read>(
+    reader: &mut R,
+    input_format: FileFormat,
+) -> Result<Vec<Record>> {
+    read_all_records(&mut flate2::read::GzDecoder::new(reader), input_format)
+}
+
+/// Write all records in given format compressing them with gzip
+#[cfg(feature = "gzip")]
+pub fn write_all_records_gz<W: Write>(
+    writer: &mut W,
+    output_format: FileFormat,
+    records: &[Record],
+) -> Result<()> {
+    let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+    write_all_records(&mut encoder, output_format, records)?;
+    encoder
+        .finish()
+        .map(|_| ())
+        .map_err(|e| YpbankError::WriteError(e.to_string()))
+}
+
+/// Same as [`read_all_records`] but records with unused user id set are rejected instead of dropping it
+pub fn read_all_records_rejecting_unused_user_ids<R: Read>(
+    reader: &mut R,
+    input_format: FileFormat,
+) -> Result<Vec<Record>> {
+    let config = ReaderConfig {
+        reject_unused_user_ids: true,
+        ..ReaderConfig::default()
+    };
+    match input_format {
+        // Binary reader takes strictness from config too, trailing bytes stay an error
+        FileFormat::Binary => BinRecordReader::with_config(ReaderConfig {
+            strict: true,
+            ..config
+        })
+        .read_all(reader),
+        format => read_all_records_with_config(reader, format, config),
+    }
+}
+
+/// Check if path has `.zst` extension
+pub fn is_zstd_path<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("zst"))
+}
+
+/// Read all records in given format from zstd compressed reader
+#[cfg(feature = "zstd")]
+pub fn read_all_records_zstd<R: Read>(
+    reader: &mut R,
+    input_format: FileFormat,
+) -> Result<Vec<Record>> {
+    let mut decoder = zstd::stream::read::Decoder::new(reader)
+        .map_err(|e| YpbankError::ReadError(e.to_string()))?;
+    read_all_records(&mut decoder, input_format)
+}
+
+/// Write all records in given format compressing them with zstd.
+///
+/// `level` is zstd compression level from 1 to 22, 0 selects default level
+#[cfg(feature = "zstd")]
+pub fn write_all_records_zstd<W: Write>(
+    writer: &mut W,
+    output_format: FileFormat,
+    records: &[Record],
+    level: i32,
+) -> Result<()> {
+    let mut encoder = zstd::stream::write::Encoder::new(writer, level)
+        .map_err(|e| YpbankError::WriteError(e.to_string()))?;
+    write_all_records(&mut encoder, output_format, records)?;
+    encoder
+        .finish()
+        .map(|_| ())
+        .map_err(|e| YpbankError::WriteError(e.to_string()))
+}
+
+/// Read all records in given format rejecting unused user ids and validate every record, first validation error is returned
+pub fn read_all_records_validated<R: Read>(
+    reader: &mut R,
+    input_format: FileFormat,
+) -> Result<Vec<Record>> {
+    let records = read_all_records_rejecting_unused_user_ids(reader, input_format)?;
+    if let Some(e) = records.iter().find_map(|r| r.validate().err()) {
+        return Err(e);
+    }
+    Ok(records)
+}
+
+/// Iterate over records in given format without reading whole input into memory
+pub fn records_iter<'a>(
+    reader: &'a mut dyn Read,
+    input_format: FileFormat,
+) -> Box<dyn Iterator<Item = Result<Record>> + 'a> {
+    match input_format {
+        FileFormat::Binary => BinRecordReader::new().records(reader),
+        FileFormat::Csv => CsvRecordReader::new().records(reader),
+        FileFormat::Text => TextRecordReader::new().records(reader),
+        FileFormat::Tsv => CsvRecordReader::with_delimiter(b'\t').records(reader),
+        FileFormat::Markdown => Box::new(std::iter::once(Err(YpbankError::ReadNotSupported(
+            FileFormat::Markdown,
+        )))),
     }
 }
 
@@ -156,10 +951,1730 @@ pub fn write_all_records<W: Write>(
     writer: &mut W,
     output_format: FileFormat,
     records: &[Record],
-) -> Result<(), YpbankError> {
-    match output_format {
-        FileFormat::Binary => BinRecordWriter::new().write_all(writer, records),
-        FileFormat::Csv => CsvRecordWriter::new().write_all(writer, records),
-        FileFormat::Text => TextRecordWriter::new().write_all(writer, records),
+) -> Result<()> {
+    write_buffered(writer, |w| match output_format {
+        FileFormat::Binary => BinRecordWriter::new().write_all(w, records),
+        FileFormat::Csv => CsvRecordWriter::new().write_all(w, records),
+        FileFormat::Text => TextRecordWriter::new().write_all(w, records),
+        FileFormat::Tsv => CsvRecordWriter::with_delimiter(b'\t').write_all(w, records),
+        FileFormat::Markdown => MarkdownRecordWriter::new().write_all(w, records),
+    })
+}
+
+/// Parse timestamp given as epoch value in `unit` or, with `timestamp` feature, as RFC 3339 string
+fn parse_timestamp(value: &str, unit: TimestampUnit) -> Option<u64> {
+    if let Ok(epoch) = value.parse::<u64>() {
+        return unit.to_millis(epoch);
+    }
+
+    #[cfg(feature = "timestamp")]
+    if let Some(millis) = timestamp::parse_rfc3339(value) {
+        return Some(millis);
+    }
+
+    None
+}
+
+/// Skip UTF-8 byte order mark at the start of input, files exported by Windows tools often begin with it.
+///
+/// Read errors are left to be reported by the following read
+fn skip_bom<R: BufRead>(reader: &mut R) {
+    const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+    if reader.fill_buf().is_ok_and(|buf| buf.starts_with(BOM)) {
+        reader.consume(BOM.len());
+    }
+}
+
+/// Run `write` over buffered writer and flush it afterwards
+fn write_buffered<W: Write>(
+    writer: &mut W,
+    write: impl FnOnce(&mut BufWriter<&mut W>) -> Result<()>,
+) -> Result<()> {
+    let mut w = BufWriter::new(writer);
+    write(&mut w)?;
+    w.flush()
+        .map_err(|e| YpbankError::WriteError(e.to_string()))
+}
+
+/// Read all CSV records separated by given delimiter, e.g. `b';'` or `b'\t'`
+pub fn read_all_csv_records_with_delimiter<R: Read>(
+    reader: &mut R,
+    delimiter: u8,
+) -> Result<Vec<Record>> {
+    CsvRecordReader::with_delimiter(delimiter).read_all(reader)
+}
+
+/// Read all CSV records from file without header row, columns are matched by position
+pub fn read_all_csv_records_without_headers<R: Read>(reader: &mut R) -> Result<Vec<Record>> {
+    CsvRecordReader::without_headers().read_all(reader)
+}
+
+/// Read all CSV records renaming source headers to canonical field names, e.g. `VALUE` to `AMOUNT`
+pub fn read_all_csv_records_with_aliases<R: Read>(
+    reader: &mut R,
+    aliases: HashMap<String, String>,
+) -> Result<Vec<Record>> {
+    CsvRecordReader::with_aliases(aliases).read_all(reader)
+}
+
+/// Read all text records renaming source fields to canonical names, e.g. `DESC` to `DESCRIPTION`
+pub fn read_all_text_records_with_aliases<R: Read>(
+    reader: &mut R,
+    aliases: HashMap<String, String>,
+) -> Result<Vec<Record>> {
+    TextRecordReader::with_aliases(aliases).read_all(reader)
+}
+
+/// Write all records as CSV separated by given delimiter
+pub fn write_all_csv_records_with_delimiter<W: Write>(
+    writer: &mut W,
+    delimiter: u8,
+    records: &[Record],
+) -> Result<()> {
+    write_buffered(writer, |w| {
+        CsvRecordWriter::with_delimiter(delimiter).write_all(w, records)
+    })
+}
+
+/// Write all records as CSV with given columns only, fails on unknown column name
+pub fn write_all_csv_records_with_columns<W: Write>(
+    writer: &mut W,
+    columns: &[&str],
+    records: &[Record],
+) -> Result<()> {
+    let csv_writer = CsvRecordWriter::with_columns(columns)?;
+    write_buffered(writer, |w| csv_writer.write_all(w, records))
+}
+
+/// Read all CSV records skipping rows that failed to parse.
+///
+/// Returns parsed records together with index of every failed row and its error
+pub fn read_all_csv_records_lenient<R: Read>(
+    reader: &mut R,
+) -> (Vec<Record>, Vec<(usize, YpbankError)>) {
+    CsvRecordReader::new().read_all_lenient(reader)
+}
+
+/// Detect file format by leading bytes of content.
+///
+/// Detection is best-effort: binary records start with magic header, CSV with `TX_ID` column header
+/// and text with `TX_ID: ` field or `#` comment. Headerless files of any format are not recognized
+pub fn detect_format(bytes: &[u8]) -> Option<FileFormat> {
+    if bin_format::starts_with_header(bytes) {
+        Some(FileFormat::Binary)
+    } else if bytes.starts_with(b"TX_ID,") || bytes.starts_with(b"\"TX_ID\",") {
+        Some(FileFormat::Csv)
+    } else if bytes.starts_with(b"TX_ID\t") || bytes.starts_with(b"\"TX_ID\"\t") {
+        Some(FileFormat::Tsv)
+    } else if bytes.starts_with(b"TX_ID: ") || bytes.starts_with(b"#") {
+        Some(FileFormat::Text)
+    } else {
+        None
+    }
+}
+
+/// Detect file format by peeking into reader buffer without consuming any data, see [detect_format]
+pub fn detect_format_buffered<R: BufRead>(reader: &mut R) -> Result<Option<FileFormat>> {
+    let bytes = reader
+        .fill_buf()
+        .map_err(|e| YpbankError::ReadError(e.to_string()))?;
+    Ok(detect_format(bytes))
+}
+
+/// Read all records in given format from file
+pub fn read_records_from_path<P: AsRef<Path>>(path: P, format: FileFormat) -> Result<Vec<Record>> {
+    let file = File::open(path).map_err(|e| YpbankError::FileOpenError(e.to_string()))?;
+    read_all_records(&mut BufReader::new(file), format)
+}
+
+/// Write all records in given format to file, existing file is truncated
+pub fn write_records_to_path<P: AsRef<Path>>(
+    path: P,
+    format: FileFormat,
+    records: &[Record],
+) -> Result<()> {
+    let mut file = File::create(path).map_err(|e| YpbankError::FileOpenError(e.to_string()))?;
+    write_all_records(&mut file, format, records)
+}
+
+/// Read all records in input format and write them in output format
+pub fn convert(
+    mut reader: &mut dyn Read,
+    input_format: FileFormat,
+    mut writer: &mut dyn Write,
+    output_format: FileFormat,
+) -> Result<()> {
+    let records = read_all_records(&mut reader, input_format)?;
+    write_all_records(&mut writer, output_format, &records)
+}
+
+/// Write all records in given text format to string, binary format is rejected
+pub fn write_all_records_to_string(format: FileFormat, records: &[Record]) -> Result<String> {
+    if format == FileFormat::Binary {
+        return Err(YpbankError::NotTextFormat(format));
+    }
+
+    let mut buffer = vec![];
+    write_all_records(&mut buffer, format, records)?;
+    String::from_utf8(buffer).map_err(|e| YpbankError::WriteError(e.to_string()))
+}
+
+/// Read all records in given format from byte slice
+pub fn read_all_records_from_slice(bytes: &[u8], format: FileFormat) -> Result<Vec<Record>> {
+    read_all_records(&mut Cursor::new(bytes), format)
+}
+
+/// Convert records held in memory from input format to output format
+pub fn convert_bytes(
+    input: &[u8],
+    input_format: FileFormat,
+    output_format: FileFormat,
+) -> Result<Vec<u8>> {
+    let mut output = Cursor::new(vec![]);
+    convert(
+        &mut Cursor::new(input),
+        input_format,
+        &mut output,
+        output_format,
+    )?;
+    Ok(output.into_inner())
+}
+
+/// Write records in given format and read them back, fails with id of the first record that changed.
+///
+/// Some values can't survive every format, e.g. empty description is read back as absent from CSV and binary
+pub fn verify_roundtrip(records: &[Record], format: FileFormat) -> Result<()> {
+    let mut buffer = vec![];
+    write_all_records(&mut buffer, format.clone(), records)?;
+    let read = read_all_records_from_slice(&buffer, format)?;
+
+    let mismatch = records
+        .iter()
+        .zip(&read)
+        .find(|(original, read)| original != read)
+        .map(|(original, _)| original)
+        .or_else(|| records.get(read.len()))
+        .or_else(|| read.get(records.len()));
+    match mismatch {
+        Some(record) => Err(YpbankError::RoundTripMismatch { id: record.id }),
+        None => Ok(()),
+    }
+}
+
+/// Read all binary records written without per-record header and length.
+///
+/// Every description occupies exactly `description_len` bytes, trailing zero bytes are treated as padding
+pub fn read_all_headerless_records<R: Read>(
+    reader: &mut R,
+    description_len: usize,
+) -> Result<Vec<Record>> {
+    BinRecordReader::new()
+        .headerless(description_len)
+        .read_all(reader)
+}
+
+/// Write all records in binary format without per-record header and length.
+///
+/// Every description is zero padded to exactly `description_len` bytes
+pub fn write_all_headerless_records<W: Write>(
+    writer: &mut W,
+    description_len: usize,
+    records: &[Record],
+) -> Result<()> {
+    write_buffered(writer, |w| {
+        BinRecordWriter::new()
+            .headerless(description_len)
+            .write_all(w, records)
+    })
+}
+
+/// Write all records in binary format with LEB128 encoded integer fields.
+///
+/// Such records are smaller for typical data and are recognized by [read_all_records] with [FileFormat::Binary]
+pub fn write_all_varint_records<W: Write>(writer: &mut W, records: &[Record]) -> Result<()> {
+    write_buffered(writer, |w| {
+        BinRecordWriter::new().varint().write_all(w, records)
+    })
+}
+
+/// Read all binary records followed by CRC32 checksum of record body
+pub fn read_all_checksummed_records<R: Read>(reader: &mut R) -> Result<Vec<Record>> {
+    BinRecordReader::with_checksums(true).read_all(reader)
+}
+
+/// Read all binary records replacing invalid UTF-8 in descriptions with U+FFFD instead of failing
+pub fn read_all_binary_records_lossy<R: Read>(reader: &mut R) -> Result<Vec<Record>> {
+    BinRecordReader::new().lossy(true).read_all(reader)
+}
+
+/// Write all records in binary format appending CRC32 checksum of body to every record
+pub fn write_all_checksummed_records<W: Write>(writer: &mut W, records: &[Record]) -> Result<()> {
+    write_buffered(writer, |w| {
+        BinRecordWriter::with_checksums(true).write_all(w, records)
+    })
+}
+
+/// Field records can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Sort by record id
+    Id,
+    /// Sort by timestamp
+    Timestamp,
+    /// Sort by amount
+    Amount,
+}
+
+impl FromStr for SortKey {
+    type Err = YpbankError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "id" => Ok(SortKey::Id),
+            "timestamp" => Ok(SortKey::Timestamp),
+            "amount" => Ok(SortKey::Amount),
+            _ => Err(YpbankError::UnknownSortKey(s.to_string())),
+        }
+    }
+}
+
+/// Sort records by given key, sort is stable so records with equal keys keep input order
+pub fn sort_records(records: &mut [Record], key: SortKey, desc: bool) {
+    let value = |record: &Record| match key {
+        SortKey::Id => record.id,
+        SortKey::Timestamp => record.timestamp(),
+        SortKey::Amount => record.amount(),
+    };
+
+    if desc {
+        records.sort_by_key(|record| std::cmp::Reverse(value(record)));
+    } else {
+        records.sort_by_key(value);
+    }
+}
+
+/// Check if record timestamp is within inclusive range, missing bound is not checked
+pub fn in_range(record: &Record, from: Option<u64>, to: Option<u64>) -> bool {
+    from.is_none_or(|from| record.timestamp() >= from)
+        && to.is_none_or(|to| record.timestamp() <= to)
+}
+
+/// Keep records with timestamp within inclusive range, fails if `from` is after `to`
+pub fn filter_range(
+    records: Vec<Record>,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<Vec<Record>> {
+    if let (Some(from), Some(to)) = (from, to)
+        && from > to
+    {
+        return Err(YpbankError::InvalidRange { from, to });
+    }
+
+    Ok(records
+        .into_iter()
+        .filter(|record| in_range(record, from, to))
+        .collect())
+}
+
+/// Check if record is of one of given kinds
+pub fn matches_type(record: &Record, kinds: &[RecordKind]) -> bool {
+    kinds.contains(&record.record_type().kind())
+}
+
+/// Trim description and collapse internal whitespace runs to single spaces, see [`normalize_with`]
+pub fn normalize(record: &mut Record) {
+    normalize_with(record, true);
+}
+
+/// Trim leading and trailing whitespace of description, also collapse internal whitespace runs if `collapse` is set.
+///
+/// Normalization is lossy, original spacing can't be restored
+pub fn normalize_with(record: &mut Record, collapse: bool) {
+    if let Some(description) = &mut record.description {
+        *description = if collapse {
+            description.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            description.trim().to_string()
+        };
+    }
+}
+
+/// Normalize every record, see [`normalize`]
+pub fn normalize_all(records: &mut [Record]) {
+    records.iter_mut().for_each(normalize);
+}
+
+/// Placeholder used by converter to replace redacted descriptions
+pub const DEFAULT_REDACTION: &str = "[REDACTED]";
+
+/// Replace every present description with `replacement`, e.g. [`DEFAULT_REDACTION`], records without description are kept as is
+pub fn redact_descriptions(records: &mut [Record], replacement: &str) {
+    for description in records.iter_mut().filter_map(|r| r.description.as_mut()) {
+        *description = replacement.to_string();
+    }
+}
+
+/// Replace user ids with opaque ids derived from `salt`, the same user always gets the same id for the same salt.
+///
+/// Distinct users never share an anonymized id and user `0` stays `0`
+pub fn anonymize_user_ids(records: &mut [Record], salt: u64) {
+    // SplitMix64 finalizer is a bijection, so distinct ids stay distinct
+    fn mix(mut z: u64) -> u64 {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    let anonymize = |user_id: u64| match user_id {
+        0 => 0,
+        // The only id mixed to 0 takes the value left unused by keeping 0 as is
+        _ => match mix(user_id ^ salt) {
+            0 => mix(salt),
+            anonymized => anonymized,
+        },
+    };
+
+    for record in records {
+        for user_id in record.record_type.user_ids_mut() {
+            *user_id = anonymize(*user_id);
+        }
+    }
+}
+
+/// Drop first `skip` records and keep at most `limit` of the rest, all of them if `limit` is `None`
+pub fn paginate(mut records: Vec<Record>, skip: usize, limit: Option<usize>) -> Vec<Record> {
+    records.drain(..skip.min(records.len()));
+    if let Some(limit) = limit {
+        records.truncate(limit);
+    }
+    records
+}
+
+/// Keep first record for every id, identical duplicates are dropped.
+///
+/// Returns [`YpbankError::ConflictingDuplicate`] if records with the same id differ
+pub fn dedup_by_id(records: Vec<Record>) -> Result<Vec<Record>> {
+    let mut seen: HashMap<u64, usize> = HashMap::new();
+    let mut unique: Vec<Record> = Vec::with_capacity(records.len());
+
+    for record in records {
+        match seen.get(&record.id) {
+            Some(&index) if unique[index] == record => {}
+            Some(_) => return Err(YpbankError::ConflictingDuplicate { id: record.id }),
+            None => {
+                seen.insert(record.id, unique.len());
+                unique.push(record);
+            }
+        }
+    }
+
+    Ok(unique)
+}
+
+/// Concatenate records from several sources in given order, optionally collapsing duplicates with [`dedup_by_id`]
+pub fn merge_records(sources: Vec<Vec<Record>>, dedup: bool) -> Result<Vec<Record>> {
+    if sources.is_empty() {
+        return Err(YpbankError::NoInputs);
+    }
+
+    let records = sources.into_iter().flatten().collect();
+    if dedup {
+        dedup_by_id(records)
+    } else {
+        Ok(records)
+    }
+}
+
+/// Validate all records and collect every failure together with index of invalid record in `records`
+pub fn validate_all(records: &[Record]) -> Vec<(usize, YpbankError)> {
+    records
+        .iter()
+        .enumerate()
+        .filter_map(|(index, record)| record.validate().err().map(|e| (index, e)))
+        .collect()
+}
+
+/// Check every record against format constraints and validation rules.
+///
+/// CSV and TSV rows that fail to parse are reported together with invalid records, other formats stop at first parse error.
+/// Every violation is reported with 1-based line of CSV row or 1-based number of record in other formats, ordered by it
+pub fn schema_violations<R: Read>(
+    reader: &mut R,
+    input_format: FileFormat,
+) -> Result<Vec<(usize, YpbankError)>> {
+    let config = ReaderConfig {
+        reject_unused_user_ids: true,
+        ..ReaderConfig::default()
+    };
+    let rows = match input_format {
+        FileFormat::Csv => CsvRecordReader::with_config(b',', config).read_all_by_line(reader),
+        FileFormat::Tsv => CsvRecordReader::with_config(b'\t', config).read_all_by_line(reader),
+        other => read_all_records_rejecting_unused_user_ids(reader, other)?
+            .into_iter()
+            .zip(1..)
+            .map(|(record, number)| (number, Ok(record)))
+            .collect(),
+    };
+
+    // Rows are already in order, every row yields at most one violation
+    Ok(rows
+        .into_iter()
+        .filter_map(|(row, result)| {
+            result
+                .and_then(|record| record.validate())
+                .err()
+                .map(|e| (row as usize, e))
+        })
+        .collect())
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        let mut binary = vec![];
+        write_all_records(
+            &mut binary,
+            FileFormat::Binary,
+            &[Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            )],
+        )
+        .expect("Should write successfully");
+
+        assert!(matches!(detect_format(&binary), Some(FileFormat::Binary)));
+        assert!(matches!(
+            detect_format(b"TX_ID,TX_TYPE,FROM_USER_ID"),
+            Some(FileFormat::Csv)
+        ));
+        assert!(matches!(
+            detect_format(b"\"TX_ID\",\"TX_TYPE\""),
+            Some(FileFormat::Csv)
+        ));
+        assert!(matches!(
+            detect_format(b"TX_ID: 1001\nTX_TYPE: DEPOSIT"),
+            Some(FileFormat::Text)
+        ));
+        assert!(matches!(
+            detect_format(b"# Record 1\nTX_ID: 1001"),
+            Some(FileFormat::Text)
+        ));
+        assert!(matches!(
+            detect_format(b"\"TX_ID\"\t\"TX_TYPE\""),
+            Some(FileFormat::Tsv)
+        ));
+        assert!(detect_format(b"{\"id\": 1}").is_none());
+        assert!(detect_format(b"").is_none());
+    }
+
+    #[test]
+    fn test_detect_format_buffered_does_not_consume() {
+        let data = "TX_ID: 1001\n";
+        let mut reader = BufReader::new(data.as_bytes());
+
+        assert!(matches!(
+            detect_format_buffered(&mut reader),
+            Ok(Some(FileFormat::Text))
+        ));
+
+        let mut content = String::new();
+        reader
+            .read_to_string(&mut content)
+            .expect("Should read successfully");
+        assert_eq!(content, data);
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert!(matches!(
+            FileFormat::from_path("records.bin"),
+            Ok(FileFormat::Binary)
+        ));
+        assert!(matches!(
+            FileFormat::from_path("dir/records.csv"),
+            Ok(FileFormat::Csv)
+        ));
+        assert!(matches!(
+            FileFormat::from_path("records.TXT"),
+            Ok(FileFormat::Text)
+        ));
+        assert!(matches!(
+            FileFormat::from_path("records.tsv"),
+            Ok(FileFormat::Tsv)
+        ));
+        assert_eq!(
+            FileFormat::from_path("records.json").map(|f| f.to_string()),
+            Err(YpbankError::UnknownFormat("records.json".to_string()))
+        );
+        assert!(FileFormat::from_path("records").is_err());
+        assert!(matches!(
+            FileFormat::from_path("records.csv.gz"),
+            Ok(FileFormat::Csv)
+        ));
+        assert!(FileFormat::from_path("records.gz").is_err());
+        assert!(is_gzip_path("records.bin.GZ"));
+        assert!(!is_gzip_path("records.bin"));
+        assert_eq!(
+            FileFormat::from_path("records.txt.zst"),
+            Ok(FileFormat::Text)
+        );
+        assert!(is_zstd_path("records.csv.ZST"));
+        assert!(!is_zstd_path("records.csv.gz"));
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn test_record_datetime() {
+        let record = Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        );
+
+        assert_eq!(
+            record.datetime().map(|dt| dt.timestamp_millis()),
+            Some(1672531200000)
+        );
+        assert_eq!(
+            record.rfc3339(),
+            Some("2023-01-01T00:00:00.000Z".to_string())
+        );
+
+        let moved = record
+            .clone()
+            .with_rfc3339("2023-01-02T00:00:00Z")
+            .expect("Should parse timestamp");
+        assert_eq!(moved.timestamp(), 1672617600000);
+        assert!(matches!(
+            record.with_rfc3339("not a date"),
+            Err(YpbankError::InvalidRecord(_))
+        ));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_round_trip() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        for format in FileFormat::all() {
+            let mut compressed = vec![];
+            write_all_records_gz(&mut compressed, format.clone(), &records)
+                .expect("Should write successfully");
+            assert!(compressed.starts_with(&[0x1f, 0x8b]));
+
+            let result = read_all_records_gz(&mut &compressed[..], format);
+            assert_eq!(result, Ok(records.clone()));
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        for format in FileFormat::all() {
+            for level in [0, 1, 19] {
+                let mut compressed = vec![];
+                write_all_records_zstd(&mut compressed, format.clone(), &records, level)
+                    .expect("Should write successfully");
+                assert!(compressed.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]));
+
+                let result = read_all_records_zstd(&mut &compressed[..], format.clone());
+                assert_eq!(result, Ok(records.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_amount_decimal() {
+        let record = |amount| {
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                amount,
+                1672531200000,
+                RecordStatus::Success,
+                None,
+            )
+        };
+
+        for (amount, scale, expected) in [
+            (50000, 2, "500.00"),
+            (12345, 2, "123.45"),
+            (5, 2, "0.05"),
+            (50, 2, "0.50"),
+            (0, 2, "0.00"),
+            (0, 0, "0"),
+            (7, 3, "0.007"),
+            (1234, 0, "1234"),
+            (1234, 4, "0.1234"),
+            (u64::MAX, 2, "184467440737095516.15"),
+        ] {
+            assert_eq!(
+                record(amount).amount_decimal(scale),
+                expected,
+                "amount {amount} with scale {scale}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_record_builder() {
+        let record = Record::builder()
+            .id(1002)
+            .record_type(RecordType::Transfer {
+                from_user_id: 501,
+                to_user_id: 502,
+            })
+            .amount(15000)
+            .timestamp(1672534800000)
+            .status(RecordStatus::Pending)
+            .build();
+
+        assert_eq!(
+            record,
+            Ok(Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Pending,
+                None,
+            ))
+        );
+    }
+
+    #[test]
+    fn test_record_currency() {
+        let record = Record::builder()
+            .id(1001)
+            .record_type(RecordType::Deposit { to_user_id: 501 })
+            .amount(50000)
+            .timestamp(1672531200000)
+            .status(RecordStatus::Success);
+
+        assert_eq!(
+            record.clone().build().map(|r| r.currency().to_string()),
+            Ok(Record::DEFAULT_CURRENCY.to_string())
+        );
+        assert_eq!(
+            record
+                .clone()
+                .currency("EUR")
+                .build()
+                .map(|r| r.currency().to_string()),
+            Ok("EUR".to_string())
+        );
+        for invalid in ["eur", "EURO", "EU", "E1R", ""] {
+            assert_eq!(
+                record.clone().currency(invalid).build(),
+                Err(YpbankError::InvalidRecord(format!(
+                    "invalid currency code {invalid}"
+                )))
+            );
+        }
+    }
+
+    #[test]
+    fn test_record_builder_validation() {
+        let builder = Record::builder()
+            .id(1001)
+            .record_type(RecordType::Deposit { to_user_id: 501 })
+            .timestamp(1672531200000)
+            .status(RecordStatus::Success);
+
+        assert_eq!(
+            builder.clone().build(),
+            Err(YpbankError::InvalidRecord("amount is not set".to_string()))
+        );
+        assert_eq!(
+            builder.amount(0).build(),
+            Err(YpbankError::InvalidRecord(
+                "record 1001 has zero amount".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_status_from_str() {
+        for status in [
+            RecordStatus::Success,
+            RecordStatus::Failure,
+            RecordStatus::Pending,
+            RecordStatus::Cancelled,
+        ] {
+            assert_eq!(status.to_string().parse::<RecordStatus>(), Ok(status));
+        }
+        assert!("CANCELED".parse::<RecordStatus>().is_err());
+    }
+
+    #[test]
+    fn test_primary_user() {
+        assert_eq!(RecordType::Deposit { to_user_id: 1 }.primary_user(), 1);
+        assert_eq!(RecordType::Withdrawal { from_user_id: 2 }.primary_user(), 2);
+        assert_eq!(
+            RecordType::Transfer {
+                from_user_id: 3,
+                to_user_id: 4
+            }
+            .primary_user(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_net_effect_deposit() {
+        let record = Record::new(
+            1,
+            RecordType::Deposit { to_user_id: 10 },
+            100,
+            1672531200000,
+            RecordStatus::Success,
+            "Deposit".to_string(),
+        );
+        assert_eq!(record.net_effect(), vec![(10, 100)]);
+    }
+
+    #[test]
+    fn test_balance_changes() {
+        let record = Record::new(
+            3,
+            RecordType::Transfer {
+                from_user_id: 10,
+                to_user_id: 20,
+            },
+            u64::MAX,
+            1672531200000,
+            RecordStatus::Success,
+            "Transfer".to_string(),
+        )
+        .with_fee(5);
+        assert_eq!(
+            record.balance_changes(),
+            vec![(10, -(u64::MAX as i128)), (20, u64::MAX as i128), (10, -5)]
+        );
+    }
+
+    #[test]
+    fn test_net_effect_withdrawal() {
+        let record = Record::new(
+            2,
+            RecordType::Withdrawal { from_user_id: 10 },
+            100,
+            1672531200000,
+            RecordStatus::Success,
+            "Withdrawal".to_string(),
+        );
+        assert_eq!(record.net_effect(), vec![(10, -100)]);
+    }
+
+    #[test]
+    fn test_net_effect_fee() {
+        let record = Record::new(
+            4,
+            RecordType::Fee { from_user_id: 10 },
+            150,
+            1672531200000,
+            RecordStatus::Success,
+            "Fee".to_string(),
+        );
+        assert_eq!(record.net_effect(), vec![(10, -150)]);
+        assert_eq!(record.record_type().primary_user(), 10);
+    }
+
+    #[test]
+    fn test_net_effect_transfer() {
+        let record = Record::new(
+            3,
+            RecordType::Transfer {
+                from_user_id: 10,
+                to_user_id: 20,
+            },
+            u64::MAX,
+            1672531200000,
+            RecordStatus::Success,
+            "Transfer".to_string(),
+        );
+        assert_eq!(record.net_effect(), vec![(10, -i64::MAX), (20, i64::MAX)]);
+    }
+
+    #[test]
+    fn test_validate_valid_record() {
+        let record = Record::new(
+            1,
+            RecordType::Transfer {
+                from_user_id: 10,
+                to_user_id: 20,
+            },
+            100,
+            1672531200000,
+            RecordStatus::Success,
+            "Transfer".to_string(),
+        );
+        assert_eq!(record.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_read_validated_zero_amount() {
+        let csv_data = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,Deposit
+1002,DEPOSIT,0,501,0,1672531200000,SUCCESS,Empty deposit
+";
+        assert!(read_all_records(&mut csv_data.as_bytes(), FileFormat::Csv).is_ok());
+        assert_eq!(
+            read_all_records_validated(&mut csv_data.as_bytes(), FileFormat::Csv),
+            Err(YpbankError::InvalidRecord(
+                "record 1002 has zero amount".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_self_transfer() {
+        let record = Record::new(
+            1,
+            RecordType::Transfer {
+                from_user_id: 10,
+                to_user_id: 10,
+            },
+            100,
+            1672531200000,
+            RecordStatus::Success,
+            "Self transfer".to_string(),
+        );
+        assert!(matches!(
+            record.validate(),
+            Err(YpbankError::InvalidRecord(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_deposit_with_sender() {
+        let csv_data = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,7,501,50000,1672531200000,SUCCESS,Deposit
+";
+        let records = read_all_records(&mut csv_data.as_bytes(), FileFormat::Csv)
+            .expect("Should read leniently");
+        assert_eq!(
+            records[0].record_type(),
+            &RecordType::Deposit { to_user_id: 501 }
+        );
+        assert!(matches!(
+            read_all_records_rejecting_unused_user_ids(&mut csv_data.as_bytes(), FileFormat::Csv),
+            Err(YpbankError::InvalidRecord(_))
+        ));
+        assert_eq!(
+            schema_violations(&mut csv_data.as_bytes(), FileFormat::Csv)
+                .expect("Should read header")
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_read_withdrawal_with_receiver() {
+        let text_data = r#"TX_ID: 1003
+TX_TYPE: WITHDRAWAL
+FROM_USER_ID: 502
+TO_USER_ID: 7
+AMOUNT: 1000
+TIMESTAMP: 1672538400000
+STATUS: PENDING
+DESCRIPTION: "ATM withdrawal"
+"#;
+        assert!(read_all_records(&mut text_data.as_bytes(), FileFormat::Text).is_ok());
+        assert!(matches!(
+            read_all_records_rejecting_unused_user_ids(&mut text_data.as_bytes(), FileFormat::Text),
+            Err(YpbankError::InvalidRecord(_))
+        ));
+
+        let mut binary = vec![];
+        convert(
+            &mut text_data
+                .replace("TO_USER_ID: 7", "TO_USER_ID: 0")
+                .as_bytes(),
+            FileFormat::Text,
+            &mut binary,
+            FileFormat::Binary,
+        )
+        .expect("Should convert");
+        // Header, version, length, id, type and sender precede big endian receiver
+        let to_user_id_at = 4 + 1 + 4 + 8 + 1 + 8;
+        binary[to_user_id_at + 7] = 7;
+        assert!(read_all_records(&mut &binary[..], FileFormat::Binary).is_ok());
+        assert!(matches!(
+            read_all_records_rejecting_unused_user_ids(&mut &binary[..], FileFormat::Binary),
+            Err(YpbankError::InvalidRecord(_))
+        ));
+    }
+
+    #[test]
+    fn test_sort_records() {
+        let record = |id, amount, timestamp| {
+            Record::new(
+                id,
+                RecordType::Deposit { to_user_id: 10 },
+                amount,
+                timestamp,
+                RecordStatus::Success,
+                None,
+            )
+        };
+        let records = vec![record(2, 300, 10), record(3, 100, 30), record(1, 200, 20)];
+        let ids = |key, desc| {
+            let mut sorted = records.clone();
+            sort_records(&mut sorted, key, desc);
+            sorted.into_iter().map(|r| r.id).collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(SortKey::Id, false), vec![1, 2, 3]);
+        assert_eq!(ids(SortKey::Id, true), vec![3, 2, 1]);
+        assert_eq!(ids(SortKey::Timestamp, false), vec![2, 1, 3]);
+        assert_eq!(ids(SortKey::Timestamp, true), vec![3, 1, 2]);
+        assert_eq!(ids(SortKey::Amount, false), vec![3, 1, 2]);
+        assert_eq!(ids(SortKey::Amount, true), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_sort_records_is_stable() {
+        let record = |id| {
+            Record::new(
+                id,
+                RecordType::Deposit { to_user_id: 10 },
+                100,
+                10,
+                RecordStatus::Success,
+                None,
+            )
+        };
+        let mut records = vec![record(2), record(1), record(3)];
+
+        sort_records(&mut records, SortKey::Amount, true);
+
+        assert_eq!(
+            records.into_iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![2, 1, 3]
+        );
+        assert_eq!("Timestamp".parse::<SortKey>(), Ok(SortKey::Timestamp));
+        assert_eq!(
+            "date".parse::<SortKey>(),
+            Err(YpbankError::UnknownSortKey("date".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_in_range() {
+        let record = Record::new(
+            1,
+            RecordType::Deposit { to_user_id: 10 },
+            100,
+            1000,
+            RecordStatus::Success,
+            "Deposit".to_string(),
+        );
+
+        assert!(in_range(&record, Some(1000), Some(1000)));
+        assert!(in_range(&record, Some(500), Some(1500)));
+        assert!(!in_range(&record, Some(1001), Some(1500)));
+        assert!(!in_range(&record, Some(500), Some(999)));
+
+        assert!(in_range(&record, Some(1000), None));
+        assert!(!in_range(&record, Some(1001), None));
+
+        assert!(in_range(&record, None, Some(1000)));
+        assert!(!in_range(&record, None, Some(999)));
+
+        assert!(in_range(&record, None, None));
+    }
+
+    #[test]
+    fn test_filter_range_inverted_bounds() {
+        assert_eq!(
+            filter_range(vec![], Some(2000), Some(1000)),
+            Err(YpbankError::InvalidRange {
+                from: 2000,
+                to: 1000
+            })
+        );
+        assert_eq!(filter_range(vec![], Some(1000), Some(1000)), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_matches_type() {
+        let fee = Record::new(
+            1,
+            RecordType::Fee { from_user_id: 10 },
+            5,
+            1672531200000,
+            RecordStatus::Success,
+            "Monthly fee".to_string(),
+        );
+
+        assert!(matches_type(&fee, &[RecordKind::Deposit, RecordKind::Fee]));
+        assert!(!matches_type(&fee, &[RecordKind::Withdrawal]));
+        assert!(!matches_type(&fee, &[]));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let record = |description: &str| {
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                description.to_string(),
+            )
+        };
+
+        let mut records = vec![
+            record("  Payment \t for\n\nservices  "),
+            record("Plain"),
+            record("   "),
+        ];
+        normalize_all(&mut records);
+        assert_eq!(
+            records,
+            vec![record("Payment for services"), record("Plain"), record("")]
+        );
+
+        let mut trimmed = record("  Payment \t for services  ");
+        normalize_with(&mut trimmed, false);
+        assert_eq!(trimmed, record("Payment \t for services"));
+    }
+
+    #[test]
+    fn test_redact_descriptions() {
+        let original = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Salary of John Doe".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                None,
+            ),
+        ];
+
+        let mut records = original.clone();
+        redact_descriptions(&mut records, DEFAULT_REDACTION);
+
+        assert_eq!(records[0].description(), Some("[REDACTED]"));
+        assert_eq!(records[1].description(), None);
+        for (redacted, original) in records.iter().zip(&original) {
+            assert_eq!(redacted.id, original.id);
+            assert_eq!(redacted.record_type(), original.record_type());
+            assert_eq!(redacted.amount(), original.amount());
+            assert_eq!(redacted.timestamp(), original.timestamp());
+            assert_eq!(redacted.status(), original.status());
+        }
+    }
+
+    #[test]
+    fn test_anonymize_user_ids() {
+        let original = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                None,
+            ),
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Success,
+                None,
+            ),
+            Record::new(
+                1003,
+                RecordType::Reversal {
+                    from_user_id: 502,
+                    to_user_id: 501,
+                    original_tx_id: 1002,
+                },
+                15000,
+                1672538400000,
+                RecordStatus::Success,
+                None,
+            ),
+            Record::new(
+                1004,
+                RecordType::Withdrawal { from_user_id: 0 },
+                100,
+                1672542000000,
+                RecordStatus::Success,
+                None,
+            ),
+        ];
+
+        let mut records = original.clone();
+        anonymize_user_ids(&mut records, 42);
+        let ids: Vec<Vec<u64>> = records.iter().map(|r| r.record_type().user_ids()).collect();
+
+        let (a, b) = (ids[1][0], ids[1][1]);
+        assert!(![0, 501, 502, b].contains(&a));
+        assert!(![0, 501, 502].contains(&b));
+        assert_eq!(ids, vec![vec![a], vec![a, b], vec![b, a], vec![0]]);
+        assert_eq!(records[2].record_type().original_tx_id(), Some(1002));
+
+        let mut same_salt = original.clone();
+        anonymize_user_ids(&mut same_salt, 42);
+        assert_eq!(same_salt, records);
+
+        let mut other_salt = original.clone();
+        anonymize_user_ids(&mut other_salt, 43);
+        assert_ne!(other_salt, records);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let records: Vec<Record> = (1..=5)
+            .map(|id| {
+                Record::new(
+                    id,
+                    RecordType::Deposit { to_user_id: 501 },
+                    100,
+                    1672531200000,
+                    RecordStatus::Success,
+                    None,
+                )
+            })
+            .collect();
+        let ids = |records: Vec<Record>| records.iter().map(|r| r.id).collect::<Vec<_>>();
+
+        assert_eq!(ids(paginate(records.clone(), 0, None)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(ids(paginate(records.clone(), 3, None)), vec![4, 5]);
+        assert_eq!(ids(paginate(records.clone(), 1, Some(2))), vec![2, 3]);
+        assert_eq!(ids(paginate(records.clone(), 4, Some(10))), vec![5]);
+        assert!(paginate(records.clone(), 10, None).is_empty());
+        assert!(paginate(records, 0, Some(0)).is_empty());
+    }
+
+    #[test]
+    fn test_dedup_by_id_collapses_identical() {
+        let deposit = Record::new(
+            1,
+            RecordType::Deposit { to_user_id: 10 },
+            100,
+            1672531200000,
+            RecordStatus::Success,
+            "Deposit".to_string(),
+        );
+        let withdrawal = Record::new(
+            2,
+            RecordType::Withdrawal { from_user_id: 10 },
+            50,
+            1672534800000,
+            RecordStatus::Success,
+            "Withdrawal".to_string(),
+        );
+
+        let records = vec![deposit.clone(), withdrawal.clone(), deposit.clone()];
+
+        assert_eq!(dedup_by_id(records), Ok(vec![deposit, withdrawal]));
+    }
+
+    #[test]
+    fn test_dedup_by_id_conflict() {
+        let deposit = Record::new(
+            1,
+            RecordType::Deposit { to_user_id: 10 },
+            100,
+            1672531200000,
+            RecordStatus::Success,
+            "Deposit".to_string(),
+        );
+        let conflicting = Record::new(
+            1,
+            RecordType::Deposit { to_user_id: 10 },
+            200,
+            1672531200000,
+            RecordStatus::Success,
+            "Deposit".to_string(),
+        );
+
+        assert_eq!(
+            dedup_by_id(vec![deposit, conflicting]),
+            Err(YpbankError::ConflictingDuplicate { id: 1 })
+        );
+    }
+
+    #[test]
+    fn test_merge_records_overlapping_ids() {
+        let record = |id, amount| {
+            Record::new(
+                id,
+                RecordType::Deposit { to_user_id: 10 },
+                amount,
+                1672531200000,
+                RecordStatus::Success,
+                None,
+            )
+        };
+        let sources = || {
+            vec![
+                vec![record(1, 100), record(2, 200)],
+                vec![record(2, 200), record(3, 300)],
+            ]
+        };
+
+        let ids = |records: Vec<Record>| records.into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(
+            merge_records(sources(), false).map(ids),
+            Ok(vec![1, 2, 2, 3])
+        );
+        assert_eq!(merge_records(sources(), true).map(ids), Ok(vec![1, 2, 3]));
+
+        let conflicting = vec![vec![record(1, 100)], vec![record(1, 150)]];
+        assert_eq!(
+            merge_records(conflicting, true),
+            Err(YpbankError::ConflictingDuplicate { id: 1 })
+        );
+        assert_eq!(merge_records(vec![], false), Err(YpbankError::NoInputs));
+    }
+
+    #[test]
+    fn test_markdown_is_write_only() {
+        assert_eq!(
+            read_all_records(&mut "| TX_ID |".as_bytes(), FileFormat::Markdown),
+            Err(YpbankError::ReadNotSupported(FileFormat::Markdown))
+        );
+        assert!(matches!(
+            FileFormat::from_path("report.md"),
+            Ok(FileFormat::Markdown)
+        ));
+        assert!(!FileFormat::all().contains(&FileFormat::Markdown));
+    }
+
+    #[test]
+    fn test_read_all_records_from_slice() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        for format in FileFormat::all() {
+            let mut bytes = vec![];
+            write_all_records(&mut bytes, format.clone(), &records)
+                .expect("Should write successfully");
+
+            assert_eq!(
+                read_all_records_from_slice(&bytes, format.clone()),
+                Ok(records.clone()),
+                "{format} should be read from slice"
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_all_records_to_string() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        assert_eq!(
+            write_all_records_to_string(FileFormat::Csv, &records),
+            Ok(r#""TX_ID","TX_TYPE","FROM_USER_ID","TO_USER_ID","AMOUNT","TIMESTAMP","STATUS","DESCRIPTION","ORIGINAL_TX_ID","CURRENCY","FEE","REFERENCE"
+"1001","DEPOSIT","0","501","50000","1672531200000","SUCCESS","Initial account funding","","XXX","0",""
+"#
+            .to_string())
+        );
+        assert_eq!(
+            write_all_records_to_string(FileFormat::Text, &records),
+            Ok(r#"TX_ID: 1001
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 501
+AMOUNT: 50000
+TIMESTAMP: 1672531200000
+STATUS: SUCCESS
+DESCRIPTION: "Initial account funding"
+CURRENCY: XXX
+FEE: 0
+REFERENCE: ""
+
+"#
+            .to_string())
+        );
+        assert_eq!(
+            write_all_records_to_string(FileFormat::Binary, &records),
+            Err(YpbankError::NotTextFormat(FileFormat::Binary))
+        );
+    }
+
+    #[test]
+    fn test_verify_roundtrip() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Withdrawal { from_user_id: 501 },
+                1000,
+                1672534800000,
+                RecordStatus::Pending,
+                None,
+            ),
+        ];
+
+        for format in FileFormat::all() {
+            assert_eq!(
+                verify_roundtrip(&records, format.clone()),
+                Ok(()),
+                "{format}"
+            );
+        }
+
+        let mut empty_description = records.clone();
+        empty_description[1] = Record::new(
+            1002,
+            RecordType::Withdrawal { from_user_id: 501 },
+            1000,
+            1672534800000,
+            RecordStatus::Pending,
+            String::new(),
+        );
+        assert_eq!(
+            verify_roundtrip(&empty_description, FileFormat::Text),
+            Ok(())
+        );
+        for format in [FileFormat::Binary, FileFormat::Csv] {
+            assert_eq!(
+                verify_roundtrip(&empty_description, format),
+                Err(YpbankError::RoundTripMismatch { id: 1002 })
+            );
+        }
+
+        assert_eq!(
+            verify_roundtrip(&records, FileFormat::Markdown),
+            Err(YpbankError::ReadNotSupported(FileFormat::Markdown))
+        );
+    }
+
+    #[test]
+    fn test_convert_bytes_chain() {
+        let csv_data = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1002,TRANSFER,501,502,15000,1672534800000,FAILURE,"Payment for services"
+"#;
+
+        let text = convert_bytes(csv_data.as_bytes(), FileFormat::Csv, FileFormat::Text)
+            .expect("Should convert CSV to text");
+        let binary = convert_bytes(&text, FileFormat::Text, FileFormat::Binary)
+            .expect("Should convert text to binary");
+
+        let expected = read_all_records(&mut csv_data.as_bytes(), FileFormat::Csv);
+        assert_eq!(
+            read_all_records(&mut text.as_slice(), FileFormat::Text),
+            expected
+        );
+        assert_eq!(
+            read_all_records(&mut binary.as_slice(), FileFormat::Binary),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_validate_all_reports_every_invalid_record() {
+        let records = vec![
+            Record::new(
+                1,
+                RecordType::Transfer {
+                    from_user_id: 10,
+                    to_user_id: 10,
+                },
+                100,
+                1672531200000,
+                RecordStatus::Success,
+                "Self transfer".to_string(),
+            ),
+            Record::new(
+                2,
+                RecordType::Deposit { to_user_id: 10 },
+                100,
+                1672531200000,
+                RecordStatus::Success,
+                "Deposit".to_string(),
+            ),
+            Record::new(
+                3,
+                RecordType::Transfer {
+                    from_user_id: 20,
+                    to_user_id: 20,
+                },
+                100,
+                1672531200000,
+                RecordStatus::Pending,
+                "Another self transfer".to_string(),
+            ),
+            Record::new(
+                4,
+                RecordType::Withdrawal { from_user_id: 10 },
+                0,
+                1672531200000,
+                RecordStatus::Success,
+                "Empty withdrawal".to_string(),
+            ),
+        ];
+
+        let failures = validate_all(&records);
+
+        assert_eq!(
+            failures.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![0, 2, 3]
+        );
+        assert!(
+            failures
+                .iter()
+                .all(|(_, e)| matches!(e, YpbankError::InvalidRecord(_)))
+        );
+    }
+
+    #[test]
+    fn test_schema_violations() {
+        let csv_data = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1002,TRANSFER,501,502,15000,1672534800000,DONE,"Bad status"
+1003,TRANSFER,501,501,15000,1672534800000,SUCCESS,"Self transfer"
+1004,WITHDRAWAL,502,0,1000,1672538400000,PENDING,"ATM withdrawal""#;
+
+        let violations = schema_violations(&mut std::io::Cursor::new(csv_data), FileFormat::Csv);
+
+        assert_eq!(
+            violations,
+            Ok(vec![
+                (
+                    3,
+                    YpbankError::CsvUnexpectedValue {
+                        row: 3,
+                        value: "DONE".to_string()
+                    }
+                ),
+                (
+                    4,
+                    YpbankError::InvalidRecord(
+                        "transfer 1003 has the same sender and receiver 501".to_string()
+                    )
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_convert_csv_to_text_and_back() {
+        let csv_data = r#""TX_ID","TX_TYPE","FROM_USER_ID","TO_USER_ID","AMOUNT","TIMESTAMP","STATUS","DESCRIPTION","ORIGINAL_TX_ID","CURRENCY","FEE","REFERENCE"
+"1001","DEPOSIT","0","501","50000","1672531200000","SUCCESS","Initial account funding","","XXX","0",""
+"1002","TRANSFER","501","502","15000","1672534800000","FAILURE","Payment for services, invoice #123","","XXX","0",""
+"#;
+
+        let mut text = vec![];
+        convert(
+            &mut csv_data.as_bytes(),
+            FileFormat::Csv,
+            &mut text,
+            FileFormat::Text,
+        )
+        .expect("Should convert CSV to text");
+
+        assert!(
+            String::from_utf8(text.clone())
+                .expect("Should be correct string")
+                .starts_with("TX_ID: 1001\nTX_TYPE: DEPOSIT\n")
+        );
+
+        let mut csv = vec![];
+        convert(&mut &text[..], FileFormat::Text, &mut csv, FileFormat::Csv)
+            .expect("Should convert text to CSV");
+
+        assert_eq!(
+            String::from_utf8(csv).expect("Should be correct string"),
+            csv_data
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_path() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1003,
+                RecordType::Withdrawal { from_user_id: 502 },
+                1000,
+                1672538400000,
+                RecordStatus::Pending,
+                "ATM withdrawal".to_string(),
+            ),
+        ];
+
+        for format in FileFormat::all() {
+            let path =
+                std::env::temp_dir().join(format!("ypbank_path_{}_{format}", std::process::id()));
+
+            write_records_to_path(&path, format.clone(), &records)
+                .expect("Should write successfully");
+            let result = read_records_from_path(&path, format);
+            std::fs::remove_file(&path).expect("Should remove file");
+
+            assert_eq!(result, Ok(records.clone()));
+        }
+    }
+
+    #[test]
+    fn test_records_iter_sums_amounts() {
+        let records = (0..100)
+            .map(|id| {
+                Record::new(
+                    id,
+                    RecordType::Deposit { to_user_id: 501 },
+                    id * 10,
+                    1672531200000,
+                    RecordStatus::Success,
+                    "Deposit".to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for format in [FileFormat::Binary, FileFormat::Csv] {
+            let mut data = vec![];
+            write_all_records(&mut data, format.clone(), &records)
+                .expect("Should write successfully");
+
+            let total = records_iter(&mut &data[..], format)
+                .map(|r| r.map(|r| r.amount()))
+                .sum::<Result<u64>>();
+
+            assert_eq!(total, Ok(49500));
+        }
+    }
+
+    #[test]
+    fn test_records_iter_stops_after_error() {
+        let csv_data = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,Deposit
+1002,DEPOSIT,0,501
+1003,DEPOSIT,0,501,50000,1672531200000,SUCCESS,Deposit
+";
+        let results = records_iter(&mut csv_data.as_bytes(), FileFormat::Csv).collect::<Vec<_>>();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn test_write_with_short_writes() {
+        struct ShortWriter(Vec<u8>);
+
+        impl Write for ShortWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(3);
+                self.0.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        for format in FileFormat::all() {
+            let mut expected = vec![];
+            write_all_records(&mut expected, format.clone(), &records)
+                .expect("Should write successfully");
+
+            let mut short = ShortWriter(vec![]);
+            write_all_records(&mut short, format, &records).expect("Should write successfully");
+
+            assert_eq!(short.0, expected);
+        }
+    }
+
+    #[test]
+    fn test_read_missing_path() {
+        let path = std::env::temp_dir().join("ypbank_path_missing_file");
+
+        assert!(matches!(
+            read_records_from_path(path, FileFormat::Csv),
+            Err(YpbankError::FileOpenError(_))
+        ));
     }
 }
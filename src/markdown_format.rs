@@ -0,0 +1,131 @@
+//! Module with writer of Markdown tables
+use std::io::Write;
+
+use crate::error::{Result, YpbankError};
+use crate::{Record, RecordWriter};
+
+/// Writer of GitHub-flavored Markdown table, the format is write-only
+pub struct MarkdownRecordWriter;
+
+impl MarkdownRecordWriter {
+    /// Writer of table with all columns
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Column names, same as CSV header
+    const COLUMNS: [&str; 12] = [
+        "TX_ID",
+        "TX_TYPE",
+        "FROM_USER_ID",
+        "TO_USER_ID",
+        "AMOUNT",
+        "TIMESTAMP",
+        "STATUS",
+        "DESCRIPTION",
+        "ORIGINAL_TX_ID",
+        "CURRENCY",
+        "FEE",
+        "REFERENCE",
+    ];
+}
+
+impl Default for MarkdownRecordWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordWriter for MarkdownRecordWriter {
+    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<()> {
+        let separator = ["---"; 12];
+        let mut rows = vec![
+            MarkdownRecordWriter::COLUMNS.map(String::from),
+            separator.map(String::from),
+        ];
+        rows.extend(records.iter().map(cells));
+
+        for row in rows {
+            if let Err(e) = writeln!(w, "| {} |", row.join(" | ")) {
+                return Err(YpbankError::WriteError(e.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Cell values of record in column order
+fn cells(record: &Record) -> [String; 12] {
+    let (from_user_id, to_user_id) = record.record_type().endpoints();
+
+    [
+        record.id.to_string(),
+        record.record_type().to_string(),
+        from_user_id.to_string(),
+        to_user_id.to_string(),
+        record.amount().to_string(),
+        record.timestamp().to_string(),
+        record.status().to_string(),
+        escape(record.description().unwrap_or_default()),
+        record
+            .record_type()
+            .original_tx_id()
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+        record.currency().to_string(),
+        record.fee().to_string(),
+        escape(record.reference()),
+    ]
+}
+
+/// Escape characters which would break table layout
+fn escape(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', "<br>")
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use crate::{RecordStatus, RecordType};
+
+    use super::*;
+
+    #[test]
+    fn test_write_all_table() {
+        let records = vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                "Invoice #123 | services".to_string(),
+            ),
+        ];
+
+        let mut buffer = vec![];
+        MarkdownRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        assert_eq!(
+            String::from_utf8(buffer).expect("Should be correct string"),
+            "| TX_ID | TX_TYPE | FROM_USER_ID | TO_USER_ID | AMOUNT | TIMESTAMP | STATUS | DESCRIPTION | ORIGINAL_TX_ID | CURRENCY | FEE | REFERENCE |
+| --- | --- | --- | --- | --- | --- | --- | --- | --- | --- | --- | --- |
+| 1001 | DEPOSIT | 0 | 501 | 50000 | 1672531200000 | SUCCESS | Initial account funding |  | XXX | 0 |  |
+| 1002 | TRANSFER | 501 | 502 | 15000 | 1672534800000 | FAILURE | Invoice #123 \\| services |  | XXX | 0 |  |
+"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Module for filtering records by time relative to current moment
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    Record,
+    error::{Result, YpbankError},
+};
+
+/// Parse duration like `7d`, `24h`, `30m` or `15s`
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let invalid = || YpbankError::InvalidDuration(s.to_string());
+
+    let split_at = s.len().checked_sub(1).ok_or_else(invalid)?;
+    let (value, unit) = s.split_at_checked(split_at).ok_or_else(invalid)?;
+    let value = value.parse::<u64>().map_err(|_| invalid())?;
+    let unit_seconds = match unit {
+        "d" => 24 * 60 * 60,
+        "h" => 60 * 60,
+        "m" => 60,
+        "s" => 1,
+        _ => return Err(invalid()),
+    };
+
+    value
+        .checked_mul(unit_seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// Current UTC time in milliseconds since epoch
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Timestamp in milliseconds which is `duration` before `now_ms`
+pub fn cutoff(now_ms: u64, duration: Duration) -> u64 {
+    now_ms.saturating_sub(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Keep records not older than `since` and not newer than `until` relative to `now_ms`
+pub fn filter_relative(
+    records: Vec<Record>,
+    now_ms: u64,
+    since: Option<Duration>,
+    until: Option<Duration>,
+) -> Vec<Record> {
+    let since = since.map_or(0, |d| cutoff(now_ms, d));
+    let until = until.map_or(u64::MAX, |d| cutoff(now_ms, d));
+
+    records
+        .into_iter()
+        .filter(|r| (since..=until).contains(&r.timestamp()))
+        .collect()
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use crate::{RecordStatus, RecordType};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            parse_duration("7d"),
+            Ok(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert_eq!(parse_duration("24h"), Ok(Duration::from_secs(24 * 60 * 60)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("15s"), Ok(Duration::from_secs(15)));
+        assert_eq!(
+            parse_duration("7w"),
+            Err(YpbankError::InvalidDuration("7w".to_string()))
+        );
+        assert_eq!(
+            parse_duration("d"),
+            Err(YpbankError::InvalidDuration("d".to_string()))
+        );
+        assert_eq!(
+            parse_duration(""),
+            Err(YpbankError::InvalidDuration("".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_cutoff() {
+        let now = 1672531200000;
+        assert_eq!(cutoff(now, Duration::from_secs(60 * 60)), 1672527600000);
+        assert_eq!(cutoff(1000, Duration::from_secs(60)), 0);
+    }
+
+    #[test]
+    fn test_filter_relative() {
+        let now = 1672531200000;
+        let day = 24 * 60 * 60 * 1000;
+        let records = [10 * day, 5 * day, day / 2]
+            .into_iter()
+            .enumerate()
+            .map(|(id, age)| {
+                Record::new(
+                    id as u64,
+                    RecordType::Deposit { to_user_id: 1 },
+                    100,
+                    now - age,
+                    RecordStatus::Success,
+                    "Deposit".to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let ids = |records: Vec<Record>| records.iter().map(|r| r.id).collect::<Vec<_>>();
+
+        let since = parse_duration("7d").ok();
+        let until = parse_duration("1d").ok();
+
+        assert_eq!(
+            ids(filter_relative(records.clone(), now, since, None)),
+            vec![1, 2]
+        );
+        assert_eq!(
+            ids(filter_relative(records.clone(), now, None, until)),
+            vec![0, 1]
+        );
+        assert_eq!(ids(filter_relative(records, now, since, until)), vec![1]);
+    }
+}
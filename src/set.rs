@@ -0,0 +1,180 @@
+//! Module with collection of records supporting common queries
+use crate::{Record, RecordKind};
+
+/// Owned list of records with query helpers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordSet(Vec<Record>);
+
+impl RecordSet {
+    /// Find record by id
+    pub fn by_id(&self, id: u64) -> Option<&Record> {
+        self.0.iter().find(|r| r.id == id)
+    }
+
+    /// Records of given kind
+    pub fn filter_type(&self, kind: RecordKind) -> RecordSet {
+        self.0
+            .iter()
+            .filter(|r| r.record_type().kind() == kind)
+            .cloned()
+            .collect()
+    }
+
+    /// Records with timestamp in inclusive range `start_ts..=end_ts`
+    pub fn between(&self, start_ts: u64, end_ts: u64) -> RecordSet {
+        self.0
+            .iter()
+            .filter(|r| (start_ts..=end_ts).contains(&r.timestamp()))
+            .cloned()
+            .collect()
+    }
+
+    /// Sum of amounts of all records regardless of their type and status
+    pub fn total_amount(&self) -> u128 {
+        crate::stats::total_amount(&self.0)
+    }
+
+    /// Records in original order
+    pub fn records(&self) -> &[Record] {
+        &self.0
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check if set has no records
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<Record>> for RecordSet {
+    fn from(value: Vec<Record>) -> Self {
+        RecordSet(value)
+    }
+}
+
+impl From<RecordSet> for Vec<Record> {
+    fn from(value: RecordSet) -> Self {
+        value.0
+    }
+}
+
+impl FromIterator<Record> for RecordSet {
+    fn from_iter<T: IntoIterator<Item = Record>>(iter: T) -> Self {
+        RecordSet(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for RecordSet {
+    type Item = Record;
+    type IntoIter = std::vec::IntoIter<Record>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a RecordSet {
+    type Item = &'a Record;
+    type IntoIter = std::slice::Iter<'a, Record>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use crate::{RecordStatus, RecordType};
+
+    use super::*;
+
+    #[allow(dead_code)]
+    fn sample() -> RecordSet {
+        vec![
+            Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            ),
+            Record::new(
+                1002,
+                RecordType::Transfer {
+                    from_user_id: 501,
+                    to_user_id: 502,
+                },
+                15000,
+                1672534800000,
+                RecordStatus::Failure,
+                "Payment for services".to_string(),
+            ),
+            Record::new(
+                1003,
+                RecordType::Withdrawal { from_user_id: 502 },
+                1000,
+                1672538400000,
+                RecordStatus::Pending,
+                "ATM withdrawal".to_string(),
+            ),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn test_by_id() {
+        let set = sample();
+
+        assert_eq!(set.by_id(1002).map(|r| r.amount()), Some(15000));
+        assert_eq!(set.by_id(9999), None);
+    }
+
+    #[test]
+    fn test_filter_type() {
+        let set = sample();
+
+        let transfers = set.filter_type(RecordKind::Transfer);
+        assert_eq!(
+            transfers.into_iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![1002]
+        );
+        assert!(set.filter_type(RecordKind::Fee).is_empty());
+    }
+
+    #[test]
+    fn test_between_is_inclusive() {
+        let set = sample();
+
+        let ids = |set: RecordSet| set.into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(set.between(1672534800000, 1672538400000)),
+            vec![1002, 1003]
+        );
+        assert_eq!(ids(set.between(0, 1672531200000)), vec![1001]);
+        assert!(ids(set.between(1672538400001, u64::MAX)).is_empty());
+    }
+
+    #[test]
+    fn test_total_amount() {
+        assert_eq!(sample().total_amount(), 66000);
+
+        let large: RecordSet = (0..3)
+            .map(|id| {
+                Record::new(
+                    id,
+                    RecordType::Deposit { to_user_id: 1 },
+                    u64::MAX,
+                    0,
+                    RecordStatus::Success,
+                    None,
+                )
+            })
+            .collect();
+        assert_eq!(large.total_amount(), u64::MAX as u128 * 3);
+    }
+}
@@ -0,0 +1,559 @@
+//! Module with aggregations over record lists
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+};
+
+use crate::{
+    Record, RecordKind, RecordStatus, RecordType,
+    error::{Result, YpbankError},
+};
+
+/// Overview of record list
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// Total number of records
+    pub count: usize,
+    /// Number of records of each kind
+    pub by_kind: BTreeMap<RecordKind, usize>,
+    /// Number of records with each status
+    pub by_status: BTreeMap<RecordStatus, usize>,
+    /// Sum of amounts of all records
+    pub total_amount: u128,
+    /// Earliest timestamp, `None` for empty list
+    pub min_timestamp: Option<u64>,
+    /// Latest timestamp, `None` for empty list
+    pub max_timestamp: Option<u64>,
+}
+
+impl Summary {
+    /// Average amount of record, `None` for empty list
+    pub fn average_amount(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total_amount as f64 / self.count as f64)
+    }
+}
+
+/// Aggregate counts, amounts and time span of records regardless of their status
+pub fn summarize(records: &[Record]) -> Summary {
+    let mut summary = Summary::default();
+
+    for record in records {
+        summary.count += 1;
+        *summary
+            .by_kind
+            .entry(record.record_type().kind())
+            .or_insert(0) += 1;
+        *summary
+            .by_status
+            .entry(record.status().clone())
+            .or_insert(0) += 1;
+        summary.min_timestamp = Some(
+            summary
+                .min_timestamp
+                .map_or(record.timestamp(), |ts| ts.min(record.timestamp())),
+        );
+        summary.max_timestamp = Some(
+            summary
+                .max_timestamp
+                .map_or(record.timestamp(), |ts| ts.max(record.timestamp())),
+        );
+    }
+
+    summary.total_amount = total_amount(records);
+    summary
+}
+
+/// Sum of amounts of all records in `u128`, so it never overflows.
+///
+/// Amounts are summed regardless of record status, failed and pending records are included
+pub fn total_amount(records: &[Record]) -> u128 {
+    records.iter().map(|r| r.amount() as u128).sum()
+}
+
+/// Sum of amounts of records of every kind, regardless of record status like [`total_amount`]
+pub fn total_amount_by_type(records: &[Record]) -> BTreeMap<RecordKind, u128> {
+    let mut totals = BTreeMap::new();
+    for record in records {
+        *totals.entry(record.record_type().kind()).or_insert(0) += record.amount() as u128;
+    }
+    totals
+}
+
+/// Net balance change of every user, only successful records are counted and fees are charged to payers
+pub fn net_balances(records: &[Record]) -> HashMap<u64, i128> {
+    let mut balances = HashMap::new();
+
+    for record in records
+        .iter()
+        .filter(|r| *r.status() == RecordStatus::Success)
+    {
+        for (user_id, change) in record.balance_changes() {
+            *balances.entry(user_id).or_insert(0) += change;
+        }
+    }
+
+    balances
+}
+
+/// Records grouped by [`RecordType::primary_user`] owning them
+///
+/// [`RecordType::primary_user`]: crate::RecordType::primary_user
+pub fn group_by_user(records: &[Record]) -> BTreeMap<u64, Vec<&Record>> {
+    let mut users: BTreeMap<u64, Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        users
+            .entry(record.record_type().primary_user())
+            .or_default()
+            .push(record);
+    }
+    users
+}
+
+/// Up to `n` records with the largest amounts in descending order, equal amounts are ordered by id
+pub fn top_by_amount(records: &[Record], n: usize) -> Vec<&Record> {
+    if n == 0 {
+        return vec![];
+    }
+
+    // Min-heap keeps `n` best records seen so far, its top is the first to be evicted
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    for (index, record) in records.iter().enumerate() {
+        heap.push(Reverse((record.amount(), Reverse(record.id), index)));
+        if heap.len() > n {
+            heap.pop();
+        }
+    }
+
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((_, _, index))| &records[index])
+        .collect()
+}
+
+/// Records grouped by UTC day index, that is number of whole days since Unix epoch
+pub fn group_by_day(records: &[Record]) -> BTreeMap<i64, Vec<&Record>> {
+    const MILLIS_PER_DAY: u64 = 86_400_000;
+
+    let mut days: BTreeMap<i64, Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        days.entry((record.timestamp() / MILLIS_PER_DAY) as i64)
+            .or_default()
+            .push(record);
+    }
+    days
+}
+
+/// Number of records in every amount bucket keyed by its lower bound, empty buckets are omitted
+pub fn amount_histogram(records: &[Record], bucket_size: u64) -> Result<BTreeMap<u64, usize>> {
+    if bucket_size == 0 {
+        return Err(YpbankError::ZeroBucketSize);
+    }
+
+    let mut histogram = BTreeMap::new();
+    for record in records {
+        *histogram
+            .entry(record.amount() / bucket_size * bucket_size)
+            .or_insert(0) += 1;
+    }
+    Ok(histogram)
+}
+
+/// Result of matching reversals with transactions they reverse, all lists are sorted by reversal id
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReversalReport {
+    /// Pairs of reversal id and id of original transaction consistent with it
+    pub matched: Vec<(u64, u64)>,
+    /// Ids of reversals whose original transaction is missing
+    pub orphans: Vec<u64>,
+    /// Ids of reversals whose original transaction has other accounts or amount
+    pub mismatched: Vec<u64>,
+}
+
+/// Match every reversal with its original transaction.
+///
+/// Reversal is consistent if it moves the same amount back, from receiver to sender of the original
+pub fn match_reversals(records: &[Record]) -> ReversalReport {
+    let by_id: HashMap<u64, &Record> = records.iter().map(|r| (r.id, r)).collect();
+
+    let mut report = ReversalReport::default();
+    for reversal in records {
+        let Some(original_tx_id) = reversal.record_type().original_tx_id() else {
+            continue;
+        };
+        let Some(original) = by_id.get(&original_tx_id) else {
+            report.orphans.push(reversal.id);
+            continue;
+        };
+
+        let (from_user_id, to_user_id) = original.record_type().endpoints();
+        if reversal.record_type().endpoints() == (to_user_id, from_user_id)
+            && reversal.amount() == original.amount()
+        {
+            report.matched.push((reversal.id, original_tx_id));
+        } else {
+            report.mismatched.push(reversal.id);
+        }
+    }
+
+    report.matched.sort_unstable();
+    report.orphans.sort_unstable();
+    report.mismatched.sort_unstable();
+    report
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn deposit(id: u64, amount: u64, timestamp: u64) -> Record {
+        Record::new(
+            id,
+            RecordType::Deposit { to_user_id: 10 },
+            amount,
+            timestamp,
+            RecordStatus::Success,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_net_balances() {
+        let records = vec![
+            Record::new(
+                1,
+                RecordType::Deposit { to_user_id: 10 },
+                u64::MAX,
+                1672531200000,
+                RecordStatus::Success,
+                "Deposit".to_string(),
+            ),
+            Record::new(
+                2,
+                RecordType::Transfer {
+                    from_user_id: 10,
+                    to_user_id: 20,
+                },
+                300,
+                1672534800000,
+                RecordStatus::Success,
+                "Transfer".to_string(),
+            ),
+            Record::new(
+                3,
+                RecordType::Withdrawal { from_user_id: 20 },
+                100,
+                1672538400000,
+                RecordStatus::Success,
+                "Withdrawal".to_string(),
+            ),
+            Record::new(
+                4,
+                RecordType::Withdrawal { from_user_id: 30 },
+                500,
+                1672538400000,
+                RecordStatus::Success,
+                "Overdraft".to_string(),
+            ),
+            Record::new(
+                5,
+                RecordType::Transfer {
+                    from_user_id: 20,
+                    to_user_id: 10,
+                },
+                1000,
+                1672542000000,
+                RecordStatus::Failure,
+                "Failed transfer".to_string(),
+            ),
+        ];
+
+        assert_eq!(
+            net_balances(&records),
+            HashMap::from([(10, u64::MAX as i128 - 300), (20, 200), (30, -500)])
+        );
+    }
+
+    #[test]
+    fn test_net_balances_with_fees() {
+        let records = vec![
+            deposit(1, 1000, 1672531200000).with_fee(5),
+            Record::new(
+                2,
+                RecordType::Transfer {
+                    from_user_id: 10,
+                    to_user_id: 20,
+                },
+                300,
+                1672534800000,
+                RecordStatus::Success,
+                None,
+            )
+            .with_fee(15),
+            Record::new(
+                3,
+                RecordType::Withdrawal { from_user_id: 20 },
+                100,
+                1672538400000,
+                RecordStatus::Success,
+                None,
+            )
+            .with_fee(2),
+            Record::new(
+                4,
+                RecordType::Transfer {
+                    from_user_id: 20,
+                    to_user_id: 10,
+                },
+                50,
+                1672542000000,
+                RecordStatus::Failure,
+                None,
+            )
+            .with_fee(7),
+        ];
+
+        assert_eq!(
+            net_balances(&records),
+            HashMap::from([(10, 1000 - 5 - 300 - 15), (20, 300 - 100 - 2)])
+        );
+    }
+
+    #[test]
+    fn test_summarize() {
+        let records = vec![
+            Record::new(
+                1,
+                RecordType::Deposit { to_user_id: 10 },
+                1000,
+                1672531200000,
+                RecordStatus::Success,
+                "Deposit".to_string(),
+            ),
+            Record::new(
+                2,
+                RecordType::Transfer {
+                    from_user_id: 10,
+                    to_user_id: 20,
+                },
+                300,
+                1672524000000,
+                RecordStatus::Failure,
+                "Transfer".to_string(),
+            ),
+            Record::new(
+                3,
+                RecordType::Deposit { to_user_id: 20 },
+                200,
+                1672538400000,
+                RecordStatus::Success,
+                "Deposit".to_string(),
+            ),
+        ];
+
+        let summary = summarize(&records);
+
+        assert_eq!(
+            summary,
+            Summary {
+                count: 3,
+                by_kind: BTreeMap::from([(RecordKind::Deposit, 2), (RecordKind::Transfer, 1)]),
+                by_status: BTreeMap::from([(RecordStatus::Success, 2), (RecordStatus::Failure, 1)]),
+                total_amount: 1500,
+                min_timestamp: Some(1672524000000),
+                max_timestamp: Some(1672538400000),
+            }
+        );
+        assert_eq!(summary.average_amount(), Some(500.0));
+        assert_eq!(summarize(&[]).average_amount(), None);
+    }
+
+    #[test]
+    fn test_group_by_user() {
+        let records = vec![
+            Record::new(
+                1,
+                RecordType::Deposit { to_user_id: 10 },
+                100,
+                1672531200000,
+                RecordStatus::Success,
+                "Deposit".to_string(),
+            ),
+            Record::new(
+                2,
+                RecordType::Transfer {
+                    from_user_id: 20,
+                    to_user_id: 10,
+                },
+                50,
+                1672531200000,
+                RecordStatus::Success,
+                "Transfer".to_string(),
+            ),
+            Record::new(
+                3,
+                RecordType::Withdrawal { from_user_id: 10 },
+                30,
+                1672531200000,
+                RecordStatus::Success,
+                "Withdrawal".to_string(),
+            ),
+        ];
+
+        let users: BTreeMap<u64, Vec<u64>> = group_by_user(&records)
+            .into_iter()
+            .map(|(user, records)| (user, records.iter().map(|r| r.id).collect()))
+            .collect();
+
+        assert_eq!(users, BTreeMap::from([(10, vec![1, 3]), (20, vec![2])]));
+    }
+
+    #[test]
+    fn test_top_by_amount() {
+        let records = vec![
+            deposit(1, 300, 1672531200000),
+            deposit(2, 900, 1672531200000),
+            deposit(3, 100, 1672531200000),
+            deposit(4, 500, 1672531200000),
+            deposit(5, 900, 1672531200000),
+        ];
+
+        let top: Vec<u64> = top_by_amount(&records, 2).iter().map(|r| r.id).collect();
+        assert_eq!(top, vec![2, 5]);
+
+        let top: Vec<u64> = top_by_amount(&records, 3).iter().map(|r| r.id).collect();
+        assert_eq!(top, vec![2, 5, 4]);
+
+        assert!(top_by_amount(&records, 0).is_empty());
+    }
+
+    #[test]
+    fn test_top_by_amount_more_than_records() {
+        let records = vec![
+            deposit(1, 300, 1672531200000),
+            deposit(2, 700, 1672531200000),
+        ];
+
+        let top: Vec<u64> = top_by_amount(&records, 10).iter().map(|r| r.id).collect();
+        assert_eq!(top, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_group_by_day() {
+        // 2023-01-01 00:00, 2023-01-01 23:59:59.999, 2023-01-02 12:00, 2023-01-05 00:00
+        let records = vec![
+            deposit(1, 100, 1672531200000),
+            deposit(2, 200, 1672617599999),
+            deposit(3, 300, 1672660800000),
+            deposit(4, 400, 1672876800000),
+        ];
+
+        let days: BTreeMap<i64, Vec<u64>> = group_by_day(&records)
+            .into_iter()
+            .map(|(day, records)| (day, records.iter().map(|r| r.id).collect()))
+            .collect();
+
+        assert_eq!(
+            days,
+            BTreeMap::from([(19358, vec![1, 2]), (19359, vec![3]), (19362, vec![4])])
+        );
+    }
+
+    #[test]
+    fn test_amount_histogram() {
+        let records = vec![
+            deposit(1, 5, 1672531200000),
+            deposit(2, 99, 1672531200000),
+            deposit(3, 100, 1672531200000),
+            deposit(4, 150, 1672531200000),
+            deposit(5, 420, 1672531200000),
+        ];
+
+        assert_eq!(
+            amount_histogram(&records, 100),
+            Ok(BTreeMap::from([(0, 2), (100, 2), (400, 1)]))
+        );
+        assert_eq!(
+            amount_histogram(&records, 1000),
+            Ok(BTreeMap::from([(0, 5)]))
+        );
+        assert_eq!(
+            amount_histogram(&records, 0),
+            Err(YpbankError::ZeroBucketSize)
+        );
+    }
+
+    #[test]
+    fn test_total_amount_near_max() {
+        let records = vec![
+            deposit(1, u64::MAX, 1672531200000),
+            deposit(2, u64::MAX - 1, 1672531200000),
+            Record::new(
+                3,
+                RecordType::Withdrawal { from_user_id: 10 },
+                u64::MAX,
+                1672531200000,
+                RecordStatus::Failure,
+                None,
+            ),
+        ];
+
+        assert_eq!(total_amount(&records), u64::MAX as u128 * 3 - 1);
+        assert_eq!(
+            total_amount_by_type(&records),
+            BTreeMap::from([
+                (RecordKind::Deposit, u64::MAX as u128 * 2 - 1),
+                (RecordKind::Withdrawal, u64::MAX as u128),
+            ])
+        );
+        assert_eq!(total_amount(&[]), 0);
+    }
+
+    #[allow(dead_code)]
+    fn reversal(id: u64, original_tx_id: u64, from_user_id: u64, amount: u64) -> Record {
+        Record::new(
+            id,
+            RecordType::Reversal {
+                from_user_id,
+                to_user_id: 10,
+                original_tx_id,
+            },
+            amount,
+            1672538400000,
+            RecordStatus::Success,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_match_reversals() {
+        let transfer = Record::new(
+            1,
+            RecordType::Transfer {
+                from_user_id: 10,
+                to_user_id: 20,
+            },
+            300,
+            1672531200000,
+            RecordStatus::Success,
+            None,
+        );
+        let records = vec![
+            reversal(5, 1, 20, 300),
+            transfer,
+            reversal(4, 99, 20, 300),
+            reversal(3, 1, 20, 200),
+            reversal(2, 1, 30, 300),
+        ];
+
+        assert_eq!(
+            match_reversals(&records),
+            ReversalReport {
+                matched: vec![(5, 1)],
+                orphans: vec![4],
+                mismatched: vec![2, 3],
+            }
+        );
+        assert_eq!(match_reversals(&[]), ReversalReport::default());
+    }
+}
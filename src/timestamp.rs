@@ -0,0 +1,43 @@
+//! Conversion of epoch millisecond timestamps to and from RFC 3339 strings
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Convert epoch milliseconds to UTC date and time
+pub fn to_datetime(millis: u64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(i64::try_from(millis).ok()?)
+}
+
+/// Format epoch milliseconds as RFC 3339 string with millisecond precision, e.g. `2023-01-01T00:00:00.000Z`
+pub fn format_rfc3339(millis: u64) -> Option<String> {
+    to_datetime(millis).map(|dt| dt.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// Parse RFC 3339 string to epoch milliseconds, moments before epoch are rejected
+pub fn parse_rfc3339(value: &str) -> Option<u64> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .and_then(|dt| u64::try_from(dt.timestamp_millis()).ok())
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_rfc3339_round_trip() {
+        assert_eq!(
+            format_rfc3339(1672531200000),
+            Some("2023-01-01T00:00:00.000Z".to_string())
+        );
+        assert_eq!(
+            parse_rfc3339("2023-01-01T00:00:00.000Z"),
+            Some(1672531200000)
+        );
+        assert_eq!(
+            parse_rfc3339("2023-01-01T03:00:00.250+03:00"),
+            Some(1672531200250)
+        );
+        assert_eq!(parse_rfc3339("1969-12-31T23:59:59Z"), None);
+        assert_eq!(parse_rfc3339("yesterday"), None);
+        assert_eq!(format_rfc3339(u64::MAX), None);
+    }
+}
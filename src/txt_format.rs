@@ -1,87 +1,217 @@
+//! Module with reader and writer of YPBankText format
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read, Write};
 
-use crate::error::YpbankError;
-use crate::{Record, RecordReader, RecordStatus, RecordType, RecordWriter};
+use crate::error::{ErrorSource, Result, YpbankError};
+use crate::{
+    ReaderConfig, Record, RecordReader, RecordStatus, RecordType, RecordWriter, TimestampUnit,
+};
 
-pub(crate) struct TextRecordReader;
+/// Reader of text records made of `KEY: VALUE` lines separated by empty line
+pub struct TextRecordReader {
+    /// Source field name to canonical field name
+    aliases: HashMap<String, String>,
+    config: ReaderConfig,
+}
 
 impl TextRecordReader {
-    pub(crate) fn new() -> Self {
-        Self
+    /// Reader ignoring unknown fields
+    pub fn new() -> Self {
+        Self {
+            aliases: HashMap::new(),
+            config: ReaderConfig::default(),
+        }
+    }
+
+    /// Reader renaming source fields to canonical names, e.g. `DESC` to `DESCRIPTION`
+    pub fn with_aliases(aliases: HashMap<String, String>) -> Self {
+        Self::new().aliases(aliases)
+    }
+
+    /// Reader trimming fields and rejecting unknown fields according to `config`
+    pub fn with_config(config: ReaderConfig) -> Self {
+        Self::new().config(config)
+    }
+
+    /// Rename source fields to canonical names, e.g. `DESC` to `DESCRIPTION`
+    pub fn aliases(mut self, aliases: HashMap<String, String>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Trim fields and reject unknown fields according to `config`
+    pub fn config(mut self, config: ReaderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Split line into field name and value
+    fn split_field<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+        if self.config.trim_whitespace {
+            line.split_once(':')
+                .map(|(key, value)| (key.trim(), value.trim()))
+        } else {
+            line.split_once(": ")
+        }
+    }
+}
+
+impl Default for TextRecordReader {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl RecordReader for TextRecordReader {
-    fn read_all<R: Read>(&self, r: &mut R) -> Result<Vec<Record>, YpbankError> {
-        let reader = BufReader::new(r);
+    fn read_all<R: Read>(&self, r: &mut R) -> Result<Vec<Record>> {
+        let mut reader = BufReader::new(r);
+        crate::skip_bom(&mut reader);
 
-        const DELIMITER: &str = ": ";
         let mut map = HashMap::new();
         let mut records = vec![];
-        for line in reader.lines() {
+        for (index, line) in reader.lines().enumerate() {
+            let line_no = index + 1;
             match line {
                 Ok(line) => {
+                    let line = line.strip_suffix('\r').unwrap_or(&line);
+                    let line = if self.config.trim_whitespace {
+                        line.trim()
+                    } else {
+                        line
+                    };
                     if line.is_empty() {
                         let fields = map.clone();
                         map.clear();
                         let text_record = TextRecord { fields };
-                        records.push(text_record.try_into()?);
+                        records.push(text_record.into_record(&self.config)?);
                         continue;
                     }
                     if line.starts_with("#") {
                         continue;
                     }
-                    match line.split_once(DELIMITER) {
+                    match self.split_field(line) {
                         Some((key, value)) => {
+                            let key = self.aliases.get(key).map_or(key, String::as_str);
+                            if self.config.strict && !TextRecord::FIELDS.contains(&key) {
+                                return Err(YpbankError::TextUnknownField {
+                                    line_no,
+                                    field: key.to_string(),
+                                });
+                            }
                             if map.contains_key(key) {
-                                return Err(YpbankError::TextDuplicateField(key.to_string()));
+                                return Err(YpbankError::TextDuplicateField {
+                                    line_no,
+                                    field: key.to_string(),
+                                });
                             }
 
                             map.insert(key.to_string(), value.to_string());
                         }
                         None => {
-                            return Err(YpbankError::TextUnableToParse(line));
+                            return Err(YpbankError::TextUnableToParse {
+                                line_no,
+                                content: line.to_string(),
+                            });
                         }
                     }
                 }
-                Err(e) => return Err(YpbankError::TextReadError(e.to_string())),
+                Err(e) => {
+                    return Err(YpbankError::TextReadError {
+                        line_no,
+                        source: ErrorSource::new(e),
+                    });
+                }
             }
         }
 
         if !map.is_empty() {
             let text_record = TextRecord { fields: map };
-            records.push(text_record.try_into()?);
+            records.push(text_record.into_record(&self.config)?);
         }
 
         Ok(records)
     }
 }
 
-pub(crate) struct TextRecordWriter;
+/// Writer of text records
+pub struct TextRecordWriter {
+    /// Write timestamps as RFC 3339 strings instead of epoch milliseconds
+    #[cfg(feature = "timestamp")]
+    human_time: bool,
+    timestamp_unit: TimestampUnit,
+}
 
 impl TextRecordWriter {
-    pub(crate) fn new() -> Self {
-        Self
+    /// Writer of timestamps as epoch milliseconds
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "timestamp")]
+            human_time: false,
+            timestamp_unit: TimestampUnit::Millis,
+        }
+    }
+
+    /// Writer of timestamps as epoch values in given unit
+    pub fn with_timestamp_unit(timestamp_unit: TimestampUnit) -> Self {
+        Self::new().timestamp_unit(timestamp_unit)
+    }
+
+    /// Writer of timestamps as RFC 3339 strings
+    #[cfg(feature = "timestamp")]
+    pub fn with_human_time() -> Self {
+        Self::new().human_time(true)
+    }
+
+    /// Write timestamps as epoch values in given unit
+    pub fn timestamp_unit(mut self, timestamp_unit: TimestampUnit) -> Self {
+        self.timestamp_unit = timestamp_unit;
+        self
+    }
+
+    /// Write timestamps as RFC 3339 strings if `human_time` is set
+    #[cfg(feature = "timestamp")]
+    pub fn human_time(mut self, human_time: bool) -> Self {
+        self.human_time = human_time;
+        self
+    }
+}
+
+impl Default for TextRecordWriter {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl RecordWriter for TextRecordWriter {
-    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<(), YpbankError> {
+    fn write_all<W: Write>(&self, w: &mut W, records: &[Record]) -> Result<()> {
         for record in records {
-            let text_record = TextRecord::from(record);
+            let mut text_record = TextRecord::from(record);
+            text_record.fields.insert(
+                "TIMESTAMP".to_string(),
+                self.timestamp_unit
+                    .from_millis(record.timestamp())
+                    .to_string(),
+            );
+
+            #[cfg(feature = "timestamp")]
+            if self.human_time
+                && let Some(formatted) = crate::timestamp::format_rfc3339(record.timestamp())
+            {
+                text_record
+                    .fields
+                    .insert("TIMESTAMP".to_string(), formatted);
+            }
 
-            for (k, v) in text_record.fields {
-                if let Err(e) = writeln!(w, "{k}: {v}") {
+            for key in TextRecord::FIELDS {
+                if let Some(v) = text_record.fields.get(key)
+                    && let Err(e) = writeln!(w, "{key}: {v}")
+                {
                     return Err(YpbankError::WriteError(e.to_string()));
                 }
             }
             if let Err(e) = writeln!(w) {
                 return Err(YpbankError::WriteError(e.to_string()));
             }
-            if let Err(e) = w.flush() {
-                return Err(YpbankError::WriteError(e.to_string()));
-            }
         }
 
         Ok(())
@@ -92,11 +222,36 @@ struct TextRecord {
     fields: HashMap<String, String>,
 }
 
+impl TextRecord {
+    /// Fields in order they are written
+    const FIELDS: [&str; 12] = [
+        "TX_ID",
+        "TX_TYPE",
+        "FROM_USER_ID",
+        "TO_USER_ID",
+        "AMOUNT",
+        "TIMESTAMP",
+        "STATUS",
+        "DESCRIPTION",
+        "ORIGINAL_TX_ID",
+        "CURRENCY",
+        "FEE",
+        "REFERENCE",
+    ];
+}
+
 impl TryInto<Record> for TextRecord {
     type Error = YpbankError;
 
     fn try_into(self) -> Result<Record, Self::Error> {
-        fn field_value(map: &HashMap<String, String>, key: &str) -> Result<String, YpbankError> {
+        self.into_record(&ReaderConfig::default())
+    }
+}
+
+impl TextRecord {
+    /// Convert to record, numeric timestamp and unused user id are handled according to `config`
+    fn into_record(self, config: &ReaderConfig) -> Result<Record> {
+        fn field_value(map: &HashMap<String, String>, key: &str) -> Result<String> {
             map.get(key)
                 .ok_or_else(|| YpbankError::TextFieldNotFound(key.to_string()))
                 .cloned()
@@ -118,70 +273,153 @@ impl TryInto<Record> for TextRecord {
         let record_type = match field_value(&self.fields, "TX_TYPE")?.as_str() {
             "DEPOSIT" => Ok(RecordType::Deposit { to_user_id }),
             "WITHDRAWAL" => Ok(RecordType::Withdrawal { from_user_id }),
+            "FEE" => Ok(RecordType::Fee { from_user_id }),
             "TRANSFER" => Ok(RecordType::Transfer {
                 from_user_id,
                 to_user_id,
             }),
+            "REVERSAL" => field_value(&self.fields, "ORIGINAL_TX_ID").and_then(|v| {
+                let original_tx_id = v.parse::<u64>().map_err(|_| {
+                    YpbankError::TextUnexpectedFieldValue("ORIGINAL_TX_ID".to_string(), v)
+                })?;
+                Ok(RecordType::Reversal {
+                    from_user_id,
+                    to_user_id,
+                    original_tx_id,
+                })
+            }),
             other => Err(YpbankError::TextUnexpectedFieldValue(
                 "TX_TYPE".to_string(),
                 other.to_string(),
             )),
         }?;
+        if config.reject_unused_user_ids {
+            record_type.check_unused_user_id(id, from_user_id, to_user_id)?;
+        }
         let amount = field_value(&self.fields, "AMOUNT").and_then(|v| {
             v.parse::<u64>()
                 .map_err(|_| YpbankError::TextUnexpectedFieldValue("AMOUNT".to_string(), v))
         })?;
         let timestamp = field_value(&self.fields, "TIMESTAMP").and_then(|v| {
-            v.parse::<u64>()
-                .map_err(|_| YpbankError::TextUnexpectedFieldValue("TIMESTAMP".to_string(), v))
+            crate::parse_timestamp(&v, config.timestamp_unit)
+                .ok_or_else(|| YpbankError::TextUnexpectedFieldValue("TIMESTAMP".to_string(), v))
         })?;
         let status = match field_value(&self.fields, "STATUS")?.as_str() {
             "SUCCESS" => Ok(RecordStatus::Success),
             "PENDING" => Ok(RecordStatus::Pending),
             "FAILURE" => Ok(RecordStatus::Failure),
+            "CANCELLED" => Ok(RecordStatus::Cancelled),
             other => Err(YpbankError::TextUnexpectedFieldValue(
                 "STATUS".to_string(),
                 other.to_string(),
             )),
         }?;
-        let description = field_value(&self.fields, "DESCRIPTION").and_then(|v| {
-            if v.len() >= 2 && v.starts_with("\"") && v.ends_with("\"") {
-                let slice = &v[1..v.len() - 1];
-                Ok(slice.to_string())
-            } else {
-                Err(YpbankError::TextUnexpectedFieldValue(
-                    "DESCRIPTION".to_string(),
-                    v,
-                ))
-            }
-        })?;
-        Ok(Record::new(
-            id,
-            record_type,
-            amount,
-            timestamp,
-            status,
-            description,
+        let description = self
+            .fields
+            .get("DESCRIPTION")
+            .map(|v| unquote("DESCRIPTION", v))
+            .transpose()?;
+        // Records written before `CURRENCY`, `FEE` and `REFERENCE` fields were added have none of them
+        let reference = self
+            .fields
+            .get("REFERENCE")
+            .map(|v| unquote("REFERENCE", v))
+            .transpose()?
+            .unwrap_or_default();
+        let fee = self
+            .fields
+            .get("FEE")
+            .map(|v| {
+                v.parse::<u64>().map_err(|_| {
+                    YpbankError::TextUnexpectedFieldValue("FEE".to_string(), v.clone())
+                })
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let currency = self
+            .fields
+            .get("CURRENCY")
+            .map_or(Record::DEFAULT_CURRENCY, String::as_str);
+        Record::new(id, record_type, amount, timestamp, status, description)
+            .with_currency(currency)
+            .map(|record| record.with_fee(fee).with_reference(reference))
+            .map_err(|_| {
+                YpbankError::TextUnexpectedFieldValue("CURRENCY".to_string(), currency.to_string())
+            })
+    }
+}
+
+/// Parse value written by [escape_description] in quotes, `key` is reported in errors
+fn unquote(key: &str, value: &str) -> Result<String> {
+    if value.len() >= 2
+        && value.starts_with('"')
+        && value.ends_with('"')
+        && let Some(unescaped) = unescape_description(&value[1..value.len() - 1])
+    {
+        Ok(unescaped)
+    } else {
+        Err(YpbankError::TextUnexpectedFieldValue(
+            key.to_string(),
+            value.to_string(),
         ))
     }
 }
 
+/// Escape description so it fits into single quoted line: `\`, `"`, CR and LF are prefixed with backslash
+fn escape_description(description: &str) -> String {
+    let mut escaped = String::with_capacity(description.len());
+    for c in description.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+/// Reverse [escape_description], returns None for unknown escape sequences or unescaped quotes
+fn unescape_description(escaped: &str) -> Option<String> {
+    let mut description = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '\\' => description.push('\\'),
+                '"' => description.push('"'),
+                'n' => description.push('\n'),
+                'r' => description.push('\r'),
+                _ => return None,
+            },
+            '"' => return None,
+            other => description.push(other),
+        }
+    }
+    Some(description)
+}
+
 impl From<&Record> for TextRecord {
     fn from(value: &Record) -> Self {
-        let (tx_type, from_user_id, to_user_id) = match value.record_type {
-            RecordType::Deposit { to_user_id } => ("DEPOSIT", 0, to_user_id),
-            RecordType::Withdrawal { from_user_id } => ("WITHDRAWAL", from_user_id, 0),
-            RecordType::Transfer {
-                from_user_id,
-                to_user_id,
-            } => ("TRANSFER", from_user_id, to_user_id),
-        };
+        let tx_type = value.record_type.kind();
+        let (from_user_id, to_user_id) = value.record_type.endpoints();
         let status = match value.status {
             RecordStatus::Success => "SUCCESS",
             RecordStatus::Failure => "FAILURE",
             RecordStatus::Pending => "PENDING",
+            RecordStatus::Cancelled => "CANCELLED",
         };
 
+        let description = value
+            .description
+            .as_ref()
+            .map(|d| ("DESCRIPTION", format!("\"{}\"", escape_description(d))));
+        let original_tx_id = value
+            .record_type
+            .original_tx_id()
+            .map(|id| ("ORIGINAL_TX_ID", id.to_string()));
+
         Self {
             fields: HashMap::from_iter(
                 vec![
@@ -192,9 +430,16 @@ impl From<&Record> for TextRecord {
                     ("AMOUNT", value.amount.to_string()),
                     ("TIMESTAMP", value.timestamp.to_string()),
                     ("STATUS", status.to_string()),
-                    ("DESCRIPTION", format!("\"{}\"", value.description)),
+                    ("CURRENCY", value.currency().to_string()),
+                    ("FEE", value.fee.to_string()),
+                    (
+                        "REFERENCE",
+                        format!("\"{}\"", escape_description(&value.reference)),
+                    ),
                 ]
                 .into_iter()
+                .chain(description)
+                .chain(original_tx_id)
                 .map(|(k, v)| (k.to_string(), v)),
             ),
         }
@@ -207,6 +452,37 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_from_record_round_trip() {
+        let records = vec![
+            Record::new(
+                1,
+                RecordType::Deposit { to_user_id: 501 },
+                u64::MAX,
+                1672531200000,
+                RecordStatus::Success,
+                "Quoted \"savings\", \\ and\nnew line".to_string(),
+            ),
+            Record::new(
+                2,
+                RecordType::Reversal {
+                    from_user_id: 502,
+                    to_user_id: 501,
+                    original_tx_id: 1,
+                },
+                100,
+                1672534800000,
+                RecordStatus::Cancelled,
+                None,
+            ),
+        ];
+
+        for record in records {
+            let result: Result<Record> = TextRecord::from(&record).try_into();
+            assert_eq!(result, Ok(record));
+        }
+    }
+
     #[test]
     fn test_text_deposit_success() {
         let deposit = TextRecord {
@@ -225,7 +501,7 @@ mod tests {
                 .map(|(k, v)| (k.to_string(), v.to_string())),
             ),
         };
-        let result: Result<Record, YpbankError> = deposit.try_into();
+        let result: Result<Record> = deposit.try_into();
         assert_eq!(
             result,
             Ok(Record::new(
@@ -259,7 +535,7 @@ mod tests {
                 .map(|(k, v)| (k.to_string(), v.to_string())),
             ),
         };
-        let result: Result<Record, YpbankError> = deposit.try_into();
+        let result: Result<Record> = deposit.try_into();
         assert_eq!(
             result,
             Ok(Record::new(
@@ -294,7 +570,7 @@ mod tests {
                 .map(|(k, v)| (k.to_string(), v.to_string())),
             ),
         };
-        let result: Result<Record, YpbankError> = deposit.try_into();
+        let result: Result<Record> = deposit.try_into();
         assert_eq!(
             result,
             Ok(Record::new(
@@ -327,7 +603,7 @@ mod tests {
                 .map(|(k, v)| (k.to_string(), v.to_string())),
             ),
         };
-        let result: Result<Record, YpbankError> = deposit.try_into();
+        let result: Result<Record> = deposit.try_into();
         assert_eq!(
             result,
             Err(YpbankError::TextFieldNotFound("TX_ID".to_string()))
@@ -352,7 +628,7 @@ mod tests {
                 .map(|(k, v)| (k.to_string(), v.to_string())),
             ),
         };
-        let result: Result<Record, YpbankError> = deposit.try_into();
+        let result: Result<Record> = deposit.try_into();
         assert_eq!(
             result,
             Err(YpbankError::TextUnexpectedFieldValue(
@@ -459,7 +735,391 @@ DESCRIPTION: "Terminal deposit""#;
 
         assert_eq!(
             records,
-            Err(YpbankError::TextDuplicateField("TX_ID".to_string()))
+            Err(YpbankError::TextDuplicateField {
+                line_no: 3,
+                field: "TX_ID".to_string()
+            })
         )
     }
+
+    #[test]
+    fn test_read_all_strict_and_lenient() {
+        let text_data = "TX_ID: 1001
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 501
+CHANNEL: ATM
+  AMOUNT:  50000\t
+TIMESTAMP: 1672531200000
+STATUS: SUCCESS
+";
+
+        let strict = TextRecordReader::with_config(ReaderConfig::strict())
+            .read_all(&mut Cursor::new(text_data));
+        assert_eq!(
+            strict,
+            Err(YpbankError::TextUnknownField {
+                line_no: 5,
+                field: "CHANNEL".to_string()
+            })
+        );
+
+        let without_unknown = text_data.replace("CHANNEL: ATM\n", "");
+        let strict = TextRecordReader::with_config(ReaderConfig::strict())
+            .read_all(&mut Cursor::new(without_unknown));
+        assert_eq!(
+            strict,
+            Err(YpbankError::TextUnknownField {
+                line_no: 5,
+                field: "  AMOUNT".to_string()
+            })
+        );
+
+        let lenient = TextRecordReader::with_config(ReaderConfig::lenient())
+            .read_all(&mut Cursor::new(text_data));
+        assert_eq!(
+            lenient,
+            Ok(vec![Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                None,
+            )])
+        );
+    }
+
+    #[test]
+    fn test_read_all_with_bom() {
+        let text_data = "\u{feff}TX_ID: 1001
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 501
+AMOUNT: 50000
+TIMESTAMP: 1672531200000
+STATUS: SUCCESS
+DESCRIPTION: \"\u{feff}Funding\"
+";
+
+        let records = TextRecordReader::with_config(ReaderConfig::strict())
+            .read_all(&mut Cursor::new(text_data));
+
+        assert_eq!(
+            records,
+            Ok(vec![Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "\u{feff}Funding".to_string(),
+            )])
+        );
+    }
+
+    #[test]
+    fn test_read_all_with_aliases() {
+        let text_data = r#"ID: 1001
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 501
+AMOUNT: 50000
+TIMESTAMP: 1672531200000
+STATUS: SUCCESS
+DESC: "Initial account funding"
+"#;
+        let aliases = HashMap::from([
+            ("ID".to_string(), "TX_ID".to_string()),
+            ("DESC".to_string(), "DESCRIPTION".to_string()),
+        ]);
+
+        let records =
+            TextRecordReader::with_aliases(aliases.clone()).read_all(&mut Cursor::new(text_data));
+        assert_eq!(
+            records,
+            Ok(vec![Record::new(
+                1001,
+                RecordType::Deposit { to_user_id: 501 },
+                50000,
+                1672531200000,
+                RecordStatus::Success,
+                "Initial account funding".to_string(),
+            )])
+        );
+
+        let duplicated = format!("TX_ID: 1001\n{text_data}");
+        let records =
+            TextRecordReader::with_aliases(aliases).read_all(&mut Cursor::new(duplicated));
+        assert_eq!(
+            records,
+            Err(YpbankError::TextDuplicateField {
+                line_no: 2,
+                field: "TX_ID".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_all_malformed_line_number() {
+        let text_data = "TX_ID: 1\nTX_TYPE: DEPOSIT\nFROM_USER_ID: 0\nTO_USER_ID: 2\nAMOUNT: 100\nTIMESTAMP: 1\nSTATUS: SUCCESS\n\n\
+TX_ID: 2\nTX_TYPE: DEPOSIT\nFROM_USER_ID: 0\nTO_USER_ID: 2\nAMOUNT: 100\nTIMESTAMP: 1\nSTATUS: SUCCESS\n\n\
+TX_ID: 3\nTX_TYPE: DEPOSIT\nAMOUNT 100\n";
+
+        let error = TextRecordReader::new()
+            .read_all(&mut Cursor::new(text_data))
+            .expect_err("Should fail on malformed line");
+
+        assert_eq!(
+            error,
+            YpbankError::TextUnableToParse {
+                line_no: 19,
+                content: "AMOUNT 100".to_string()
+            }
+        );
+        assert_eq!(error.to_string(), "Unable to parse txt line 19: AMOUNT 100");
+    }
+
+    #[test]
+    fn test_write_all_field_order() {
+        let records = vec![
+            Record::new(
+                1234567890123456,
+                RecordType::Deposit {
+                    to_user_id: 9876543210987654,
+                },
+                10000,
+                1633036800000,
+                RecordStatus::Success,
+                "Terminal deposit".to_string(),
+            ),
+            Record::new(
+                2312321321321321,
+                RecordType::Transfer {
+                    from_user_id: 1231231231231231,
+                    to_user_id: 9876543210987654,
+                },
+                1000,
+                1633056800000,
+                RecordStatus::Failure,
+                "User transfer".to_string(),
+            ),
+        ];
+
+        let mut buffer: Vec<u8> = Vec::new();
+
+        TextRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        assert_eq!(
+            String::from_utf8(buffer).expect("Should be correct string"),
+            r#"TX_ID: 1234567890123456
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 9876543210987654
+AMOUNT: 10000
+TIMESTAMP: 1633036800000
+STATUS: SUCCESS
+DESCRIPTION: "Terminal deposit"
+CURRENCY: XXX
+FEE: 0
+REFERENCE: ""
+
+TX_ID: 2312321321321321
+TX_TYPE: TRANSFER
+FROM_USER_ID: 1231231231231231
+TO_USER_ID: 9876543210987654
+AMOUNT: 1000
+TIMESTAMP: 1633056800000
+STATUS: FAILURE
+DESCRIPTION: "User transfer"
+CURRENCY: XXX
+FEE: 0
+REFERENCE: ""
+
+"#
+        )
+    }
+
+    #[test]
+    fn test_read_all_crlf() {
+        let text_data = "# Record 1 (Deposit)\r\n\
+TX_ID: 1234567890123456\r\n\
+TX_TYPE: DEPOSIT\r\n\
+FROM_USER_ID: 0\r\n\
+TO_USER_ID: 9876543210987654\r\n\
+AMOUNT: 10000\r\n\
+TIMESTAMP: 1633036800000\r\n\
+STATUS: SUCCESS\r\n\
+DESCRIPTION: \"Terminal deposit\"\r\n\
+\r\n\
+# Record 2 (Withdrawal)\r\n\
+TX_ID: 3213213213213213\r\n\
+TX_TYPE: WITHDRAWAL\r\n\
+FROM_USER_ID: 9876543210987654\r\n\
+TO_USER_ID: 0\r\n\
+AMOUNT: 100\r\n\
+TIMESTAMP: 1633066800000\r\n\
+STATUS: SUCCESS\r\n\
+DESCRIPTION: \"User withdrawal\"\r\n";
+
+        let mut cursor = Cursor::new(text_data);
+
+        let records = TextRecordReader::new().read_all(&mut cursor);
+
+        assert_eq!(
+            records,
+            Ok(vec![
+                Record::new(
+                    1234567890123456,
+                    RecordType::Deposit {
+                        to_user_id: 9876543210987654
+                    },
+                    10000,
+                    1633036800000,
+                    RecordStatus::Success,
+                    "Terminal deposit".to_string(),
+                ),
+                Record::new(
+                    3213213213213213,
+                    RecordType::Withdrawal {
+                        from_user_id: 9876543210987654,
+                    },
+                    100,
+                    1633066800000,
+                    RecordStatus::Success,
+                    "User withdrawal".to_string(),
+                )
+            ])
+        )
+    }
+
+    #[test]
+    fn test_description_escaping_round_trip() {
+        let records = vec![Record::new(
+            1234567890123456,
+            RecordType::Deposit {
+                to_user_id: 9876543210987654,
+            },
+            10000,
+            1633036800000,
+            RecordStatus::Success,
+            "He said \"hi\"\nbye \\n".to_string(),
+        )];
+
+        let mut buffer: Vec<u8> = Vec::new();
+        TextRecordWriter::new()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let text = String::from_utf8(buffer).expect("Should be correct string");
+        assert!(text.contains(r#"DESCRIPTION: "He said \"hi\"\nbye \\n""#));
+
+        let result = TextRecordReader::new().read_all(&mut Cursor::new(text));
+
+        assert_eq!(result, Ok(records));
+    }
+
+    #[test]
+    fn test_description_unescaped_quote_error() {
+        assert_eq!(unescape_description(r#"He said "hi""#), None);
+        assert_eq!(unescape_description(r"trailing \"), None);
+        assert_eq!(unescape_description(r"unknown \t"), None);
+    }
+
+    #[test]
+    fn test_read_all_description_with_delimiter() {
+        let text_data = r#"TX_ID: 1234567890123456
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 9876543210987654
+AMOUNT: 10000
+TIMESTAMP: 1633036800000
+STATUS: SUCCESS
+DESCRIPTION: "invoice: 2024: final""#;
+
+        let records = TextRecordReader::new().read_all(&mut Cursor::new(text_data));
+
+        assert_eq!(
+            records,
+            Ok(vec![Record::new(
+                1234567890123456,
+                RecordType::Deposit {
+                    to_user_id: 9876543210987654
+                },
+                10000,
+                1633036800000,
+                RecordStatus::Success,
+                "invoice: 2024: final".to_string(),
+            )])
+        )
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn test_human_time_round_trip() {
+        let records = vec![Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        )];
+
+        let mut buffer = vec![];
+        TextRecordWriter::with_human_time()
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+
+        let text = String::from_utf8(buffer).expect("Should be correct string");
+        assert!(text.contains("TIMESTAMP: 2023-01-01T00:00:00.000Z\n"));
+
+        let from_human = TextRecordReader::new().read_all(&mut text.as_bytes());
+        let from_millis = TextRecordReader::new().read_all(
+            &mut text
+                .replace("2023-01-01T00:00:00.000Z", "1672531200000")
+                .as_bytes(),
+        );
+        assert_eq!(from_human, Ok(records.clone()));
+        assert_eq!(from_millis, Ok(records));
+    }
+
+    #[test]
+    fn test_seconds_timestamp_unit() {
+        let text = r#"TX_ID: 1001
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 501
+AMOUNT: 50000
+TIMESTAMP: 1672531200
+STATUS: SUCCESS
+DESCRIPTION: "Initial account funding"
+
+"#;
+        let config = ReaderConfig {
+            timestamp_unit: TimestampUnit::Seconds,
+            ..ReaderConfig::lenient()
+        };
+
+        let records = TextRecordReader::with_config(config)
+            .read_all(&mut text.as_bytes())
+            .expect("Should read successfully");
+        assert_eq!(records[0].timestamp(), 1672531200000);
+
+        let mut buffer = vec![];
+        TextRecordWriter::with_timestamp_unit(TimestampUnit::Seconds)
+            .write_all(&mut buffer, &records)
+            .expect("Should write successfully");
+        let written = String::from_utf8(buffer).expect("Should be correct string");
+        assert!(written.contains("TIMESTAMP: 1672531200\n"));
+
+        let overflow = text.replace("1672531200", &u64::MAX.to_string());
+        assert!(
+            TextRecordReader::with_config(config)
+                .read_all(&mut overflow.as_bytes())
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,107 @@
+use std::{fs, process::Command};
+
+#[test]
+fn test_compare_records_differing_in_fee() {
+    let header = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION,ORIGINAL_TX_ID,CURRENCY,FEE";
+    let file1 =
+        std::env::temp_dir().join(format!("ypbank_comparer_fee1_{}.csv", std::process::id()));
+    let file2 =
+        std::env::temp_dir().join(format!("ypbank_comparer_fee2_{}.csv", std::process::id()));
+    fs::write(
+        &file1,
+        format!("{header}\n1002,TRANSFER,501,502,15000,1672534800000,SUCCESS,\"Payment\",,EUR,0\n"),
+    )
+    .expect("Should write first file");
+    fs::write(
+        &file2,
+        format!(
+            "{header}\n1002,TRANSFER,501,502,15000,1672534800000,SUCCESS,\"Payment\",,EUR,25\n"
+        ),
+    )
+    .expect("Should write second file");
+
+    let compare = |ignored: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_comparer"))
+            .arg("--file1")
+            .arg(&file1)
+            .args(["--format1", "csv"])
+            .arg("--file2")
+            .arg(&file2)
+            .args(["--format2", "csv"])
+            .args(ignored)
+            .output()
+            .expect("Should run comparer binary")
+    };
+    let output = compare(&[]);
+    let ignoring_fee = compare(&["--ignore", "fee"]);
+
+    fs::remove_file(&file1).expect("Should remove first file");
+    fs::remove_file(&file2).expect("Should remove second file");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("Should be correct string"),
+        "Transactions that differs in file1 and file2: 1002\n  1002 fee: 0 != 25\n"
+    );
+    assert_eq!(
+        String::from_utf8(ignoring_fee.stdout).expect("Should be correct string"),
+        "Transactions are the same\n"
+    );
+}
+
+#[test]
+fn test_compare_strict_reports_every_invalid_record() {
+    let header = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION";
+    let file1 = std::env::temp_dir().join(format!(
+        "ypbank_comparer_strict1_{}.csv",
+        std::process::id()
+    ));
+    let file2 = std::env::temp_dir().join(format!(
+        "ypbank_comparer_strict2_{}.csv",
+        std::process::id()
+    ));
+    let records = format!(
+        "{header}\n1001,TRANSFER,501,501,100,1672531200000,SUCCESS,\"Self transfer\"\n\
+         1002,DEPOSIT,0,501,100,1672531200000,SUCCESS,\"Deposit\"\n"
+    );
+    fs::write(&file1, &records).expect("Should write first file");
+    fs::write(
+        &file2,
+        records.replace("1002,DEPOSIT,0,501", "1002,TRANSFER,502,502"),
+    )
+    .expect("Should write second file");
+
+    let compare = |strict: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_comparer"))
+            .arg("--file1")
+            .arg(&file1)
+            .args(["--format1", "csv"])
+            .arg("--file2")
+            .arg(&file2)
+            .args(["--format2", "csv"])
+            .args(strict)
+            .output()
+            .expect("Should run comparer binary")
+    };
+    let lenient = compare(&[]);
+    let strict = compare(&["--strict"]);
+
+    fs::remove_file(&file1).expect("Should remove first file");
+    fs::remove_file(&file2).expect("Should remove second file");
+
+    assert!(lenient.status.success());
+    assert!(!strict.status.success());
+    let stdout = String::from_utf8(strict.stdout).expect("Should be correct string");
+    let invalid = stdout
+        .lines()
+        .map(|line| line.split(':').next().unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        invalid,
+        vec![
+            "Invalid transaction in file 1",
+            "Invalid transaction in file 2",
+            "Invalid transaction in file 2"
+        ]
+    );
+}
@@ -0,0 +1,345 @@
+use std::{
+    fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use ypbank_converter::{FileFormat, convert};
+
+const CSV_DATA: &str = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+"#;
+
+const TEXT_DATA: &str = r#"TX_ID: 1001
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 501
+AMOUNT: 50000
+TIMESTAMP: 1672531200000
+STATUS: SUCCESS
+DESCRIPTION: "Initial account funding"
+CURRENCY: XXX
+FEE: 0
+REFERENCE: ""
+
+"#;
+
+fn run_converter_with_stdin(args: &[&str], stdin: &str) -> std::process::Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_converter"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Should run converter binary");
+
+    child
+        .stdin
+        .take()
+        .expect("Should have stdin")
+        .write_all(stdin.as_bytes())
+        .expect("Should write stdin");
+
+    child.wait_with_output().expect("Should finish")
+}
+
+#[test]
+fn test_convert_from_stdin() {
+    let output = run_converter_with_stdin(
+        &["--input-format", "csv", "--output-format", "text"],
+        CSV_DATA,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("Should be correct string"),
+        TEXT_DATA
+    );
+}
+
+#[test]
+fn test_convert_from_dash_stdin_with_detected_format() {
+    let output = run_converter_with_stdin(&["--input", "-", "--output-format", "text"], CSV_DATA);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("Should be correct string"),
+        TEXT_DATA
+    );
+}
+
+#[test]
+fn test_convert_to_output_file() {
+    let input =
+        std::env::temp_dir().join(format!("ypbank_converter_input_{}.csv", std::process::id()));
+    let output = std::env::temp_dir().join(format!(
+        "ypbank_converter_output_{}.bin",
+        std::process::id()
+    ));
+    fs::write(&input, CSV_DATA).expect("Should write input file");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_converter"))
+        .arg("--input")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .args(["--output-format", "binary"])
+        .status()
+        .expect("Should run converter binary");
+
+    let written = fs::read(&output).expect("Should read output file");
+    fs::remove_file(&input).expect("Should remove input file");
+    fs::remove_file(&output).expect("Should remove output file");
+
+    let mut expected = vec![];
+    convert(
+        &mut CSV_DATA.as_bytes(),
+        FileFormat::Csv,
+        &mut expected,
+        FileFormat::Binary,
+    )
+    .expect("Should convert in memory");
+
+    assert!(status.success());
+    assert_eq!(written, expected);
+}
+
+#[test]
+fn test_failed_convert_keeps_previous_output() {
+    let output = std::env::temp_dir().join(format!(
+        "ypbank_converter_previous_{}.txt",
+        std::process::id()
+    ));
+    let mut partial = output.clone().into_os_string();
+    partial.push(".partial");
+    fs::write(&output, "previous").expect("Should write previous output");
+    let args = [
+        "--input-format",
+        "csv",
+        "--output-format",
+        "text",
+        "--output",
+        output.to_str().expect("Should be UTF-8 path"),
+    ];
+
+    let failed = run_converter_with_stdin(&args, &CSV_DATA.replace("STATUS", "STATE"));
+    let kept = fs::read_to_string(&output).expect("Should read output file");
+    let partial_left = std::path::Path::new(&partial).exists();
+    let converted = run_converter_with_stdin(&args, CSV_DATA);
+    let replaced = fs::read_to_string(&output).expect("Should read output file");
+    fs::remove_file(&output).expect("Should remove output file");
+
+    assert!(!failed.status.success());
+    assert_eq!(kept, "previous");
+    assert!(!partial_left);
+    assert!(converted.status.success());
+    assert_eq!(replaced, TEXT_DATA);
+}
+
+#[test]
+fn test_convert_with_validate() {
+    let self_transfer = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1002,TRANSFER,501,501,15000,1672534800000,SUCCESS,"Self transfer"
+"#;
+
+    let output = run_converter_with_stdin(
+        &["--input-format", "csv", "--output-format", "text"],
+        self_transfer,
+    );
+    assert!(output.status.success());
+
+    let output = run_converter_with_stdin(
+        &[
+            "--input-format",
+            "csv",
+            "--output-format",
+            "text",
+            "--validate",
+        ],
+        self_transfer,
+    );
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = run_converter_with_stdin(
+        &[
+            "--input-format",
+            "csv",
+            "--output-format",
+            "text",
+            "--validate",
+        ],
+        CSV_DATA,
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_convert_with_type_filter() {
+    let mixed = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1002,TRANSFER,501,502,15000,1672534800000,SUCCESS,"Payment for services"
+1003,WITHDRAWAL,502,0,1000,1672538400000,PENDING,"ATM withdrawal"
+1004,TRANSFER,502,501,500,1672542000000,FAILURE,"Refund"
+"#;
+
+    let output = run_converter_with_stdin(
+        &[
+            "--input-format",
+            "csv",
+            "--output-format",
+            "csv",
+            "--type",
+            "TRANSFER",
+        ],
+        mixed,
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("Should be correct string");
+    let rows = stdout.lines().skip(1).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 2);
+    assert!(rows.iter().all(|row| row.contains(r#""TRANSFER""#)));
+}
+
+#[test]
+fn test_convert_with_redact() {
+    let output = run_converter_with_stdin(
+        &[
+            "--input-format",
+            "csv",
+            "--output-format",
+            "text",
+            "--redact",
+        ],
+        CSV_DATA,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("Should be correct string"),
+        TEXT_DATA.replace("Initial account funding", "[REDACTED]")
+    );
+}
+
+#[test]
+fn test_convert_with_skip_and_limit() {
+    let records = r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"First"
+1002,DEPOSIT,0,502,15000,1672534800000,SUCCESS,"Second"
+1003,DEPOSIT,0,503,1000,1672538400000,SUCCESS,"Third"
+1004,DEPOSIT,0,504,500,1672542000000,SUCCESS,"Fourth"
+"#;
+
+    for (options, expected) in [
+        (vec!["--skip", "3"], vec!["1004"]),
+        (vec!["--limit", "2"], vec!["1001", "1002"]),
+        (vec!["--skip", "1", "--limit", "2"], vec!["1002", "1003"]),
+        (vec!["--skip", "10"], vec![]),
+        (vec!["--limit", "0"], vec![]),
+    ] {
+        let mut args = vec!["--input-format", "csv", "--output-format", "csv"];
+        args.extend(&options);
+        let output = run_converter_with_stdin(&args, records);
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).expect("Should be correct string");
+        let ids = stdout
+            .lines()
+            .skip(1)
+            .map(|row| {
+                row.split(',')
+                    .next()
+                    .expect("Should have id")
+                    .trim_matches('"')
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ids, expected, "{options:?}");
+    }
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_convert_to_gzip_output() {
+    let output = std::env::temp_dir().join(format!(
+        "ypbank_converter_output_{}.txt.gz",
+        std::process::id()
+    ));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_converter"))
+        .args(["--input-format", "csv", "--output-format", "text"])
+        .arg("--output")
+        .arg(&output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("Should run converter binary");
+    child
+        .stdin
+        .take()
+        .expect("Should have stdin")
+        .write_all(CSV_DATA.as_bytes())
+        .expect("Should write stdin");
+    let status = child.wait().expect("Should finish");
+
+    let compressed = fs::read(&output).expect("Should read output file");
+    fs::remove_file(&output).expect("Should remove output file");
+
+    assert!(status.success());
+    let mut text = vec![];
+    std::io::Read::read_to_end(
+        &mut flate2::read::GzDecoder::new(&compressed[..]),
+        &mut text,
+    )
+    .expect("Should be complete gzip stream");
+    assert_eq!(
+        String::from_utf8(text).expect("Should be correct string"),
+        TEXT_DATA
+    );
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_convert_to_zstd_output_with_level() {
+    let output = std::env::temp_dir().join(format!(
+        "ypbank_converter_output_{}.bin.zst",
+        std::process::id()
+    ));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_converter"))
+        .args(["--input-format", "csv", "--output-format", "binary"])
+        .args(["--zstd-level", "19"])
+        .arg("--output")
+        .arg(&output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("Should run converter binary");
+    child
+        .stdin
+        .take()
+        .expect("Should have stdin")
+        .write_all(CSV_DATA.as_bytes())
+        .expect("Should write stdin");
+    let status = child.wait().expect("Should finish");
+
+    let compressed = fs::read(&output).expect("Should read output file");
+    fs::remove_file(&output).expect("Should remove output file");
+
+    assert!(status.success());
+    assert_eq!(
+        ypbank_converter::read_all_records_zstd(&mut &compressed[..], FileFormat::Binary),
+        ypbank_converter::read_all_records(&mut CSV_DATA.as_bytes(), FileFormat::Csv)
+    );
+
+    let output = run_converter_with_stdin(
+        &[
+            "--input-format",
+            "csv",
+            "--output-format",
+            "text",
+            "--zstd-level",
+            "23",
+        ],
+        CSV_DATA,
+    );
+    assert!(!output.status.success());
+}
@@ -0,0 +1,394 @@
+use std::io::Cursor;
+
+use ypbank_converter::{
+    FileFormat, Record, RecordStatus, RecordType, count_records, read_all_records,
+    write_all_records,
+};
+
+/// Write records in every format and check they are read back unchanged
+fn assert_round_trips(records: &[Record]) {
+    for format in FileFormat::all() {
+        let mut buffer = vec![];
+        write_all_records(&mut buffer, format.clone(), records)
+            .unwrap_or_else(|e| panic!("{format} should write successfully: {e}"));
+
+        let result = read_all_records(&mut Cursor::new(buffer), format.clone());
+        assert_eq!(result.as_deref(), Ok(records), "{format} round trip failed");
+    }
+}
+
+#[test]
+fn test_all_formats_round_trip() {
+    let records = vec![
+        Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        ),
+        Record::new(
+            1002,
+            RecordType::Transfer {
+                from_user_id: 501,
+                to_user_id: 502,
+            },
+            15000,
+            1672534800000,
+            RecordStatus::Failure,
+            "Payment for services, invoice #123".to_string(),
+        ),
+        Record::new(
+            1003,
+            RecordType::Withdrawal { from_user_id: 502 },
+            1000,
+            1672538400000,
+            RecordStatus::Pending,
+            "ATM withdrawal".to_string(),
+        ),
+    ];
+
+    for format in FileFormat::all() {
+        let parsed = format
+            .to_string()
+            .parse::<FileFormat>()
+            .expect("Display output should be parsable");
+        assert_eq!(parsed.to_string(), format.to_string());
+    }
+
+    assert_round_trips(&records);
+}
+
+#[test]
+fn test_absent_and_empty_description_round_trip() {
+    let record = |description: Option<String>| {
+        Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            description,
+        )
+    };
+
+    for format in FileFormat::all() {
+        let empty_read_as = match format {
+            FileFormat::Text => Some(String::new()),
+            _ => None,
+        };
+
+        for (description, expected) in [(None, None), (Some(String::new()), empty_read_as)] {
+            let mut buffer = vec![];
+            write_all_records(&mut buffer, format.clone(), &[record(description)])
+                .unwrap_or_else(|e| panic!("{format} should write successfully: {e}"));
+
+            let result = read_all_records(&mut Cursor::new(buffer), format.clone());
+            assert_eq!(
+                result,
+                Ok(vec![record(expected)]),
+                "{format} round trip failed"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_cancelled_status_round_trip() {
+    let records = vec![Record::new(
+        1004,
+        RecordType::Transfer {
+            from_user_id: 501,
+            to_user_id: 502,
+        },
+        2500,
+        1672542000000,
+        RecordStatus::Cancelled,
+        "Cancelled payment".to_string(),
+    )];
+
+    assert_round_trips(&records);
+}
+
+#[test]
+fn test_fee_round_trip() {
+    let records = vec![Record::new(
+        1005,
+        RecordType::Fee { from_user_id: 501 },
+        150,
+        1672545600000,
+        RecordStatus::Success,
+        "Monthly maintenance fee".to_string(),
+    )];
+
+    assert_round_trips(&records);
+}
+
+#[test]
+fn test_reversal_round_trip() {
+    let records = vec![
+        Record::new(
+            1002,
+            RecordType::Transfer {
+                from_user_id: 501,
+                to_user_id: 502,
+            },
+            15000,
+            1672534800000,
+            RecordStatus::Success,
+            "Payment for services".to_string(),
+        ),
+        Record::new(
+            1006,
+            RecordType::Reversal {
+                from_user_id: 502,
+                to_user_id: 501,
+                original_tx_id: 1002,
+            },
+            15000,
+            1672549200000,
+            RecordStatus::Success,
+            "Chargeback".to_string(),
+        ),
+    ];
+
+    assert_round_trips(&records);
+}
+
+#[test]
+fn test_tsv_round_trip_with_tab_in_description() {
+    let tsv_data =
+        "TX_ID\tTX_TYPE\tFROM_USER_ID\tTO_USER_ID\tAMOUNT\tTIMESTAMP\tSTATUS\tDESCRIPTION
+1001\tDEPOSIT\t0\t501\t50000\t1672531200000\tSUCCESS\tInitial account funding
+1002\tTRANSFER\t501\t502\t15000\t1672534800000\tFAILURE\t\"Payment\tfor services\"
+";
+
+    let records = read_all_records(&mut tsv_data.as_bytes(), FileFormat::Tsv)
+        .expect("Should read TSV successfully");
+    assert_eq!(records[1].description(), Some("Payment\tfor services"));
+
+    let mut buffer = vec![];
+    write_all_records(&mut buffer, FileFormat::Tsv, &records).expect("Should write successfully");
+    let written = String::from_utf8(buffer.clone()).expect("Should be correct string");
+    assert!(written.contains("\"Payment\tfor services\""));
+
+    assert_eq!(
+        read_all_records(&mut Cursor::new(buffer), FileFormat::Tsv),
+        Ok(records)
+    );
+}
+
+#[test]
+fn test_count_records_matches_read_all() {
+    let records: Vec<Record> = (0..5)
+        .map(|i| {
+            Record::new(
+                1000 + i,
+                RecordType::Deposit { to_user_id: 501 },
+                100 * i,
+                1672531200000 + i,
+                RecordStatus::Success,
+                format!("Deposit {i}"),
+            )
+        })
+        .collect();
+
+    for format in FileFormat::all() {
+        let mut buffer = vec![];
+        write_all_records(&mut buffer, format.clone(), &records)
+            .unwrap_or_else(|e| panic!("{format} should write successfully: {e}"));
+
+        let read = read_all_records(&mut Cursor::new(&buffer), format.clone())
+            .unwrap_or_else(|e| panic!("{format} should read successfully: {e}"));
+        assert_eq!(
+            count_records(&mut Cursor::new(&buffer), format.clone()),
+            Ok(read.len()),
+            "{format} count differs"
+        );
+    }
+}
+
+#[test]
+fn test_round_trip_parity_with_tricky_descriptions() {
+    let descriptions = [
+        // Empty description is read back as absent by design, see test above
+        None,
+        Some("Plain"),
+        Some("Comma, separated"),
+        Some("Quoted \"savings\" account"),
+        Some("\"Fully quoted\""),
+        Some("Back\\slash and \\\" escaped quote"),
+        Some("Multi\nline\r\ntext"),
+        Some("Tab\tinside"),
+        Some("  Leading and trailing spaces  "),
+        Some("Key: value"),
+        Some("Unicode: перевод €"),
+    ];
+    let record_types = [
+        RecordType::Deposit { to_user_id: 501 },
+        RecordType::Withdrawal { from_user_id: 502 },
+        RecordType::Fee { from_user_id: 503 },
+        RecordType::Transfer {
+            from_user_id: 501,
+            to_user_id: u64::MAX,
+        },
+        RecordType::Reversal {
+            from_user_id: 502,
+            to_user_id: 501,
+            original_tx_id: 1001,
+        },
+    ];
+    let statuses = [
+        RecordStatus::Success,
+        RecordStatus::Failure,
+        RecordStatus::Pending,
+        RecordStatus::Cancelled,
+    ];
+
+    let records: Vec<Record> = descriptions
+        .iter()
+        .enumerate()
+        .map(|(i, description)| {
+            Record::new(
+                i as u64 + 1,
+                record_types[i % record_types.len()].clone(),
+                if i == 0 { u64::MAX } else { i as u64 * 1000 },
+                1672531200000 + i as u64,
+                statuses[i % statuses.len()].clone(),
+                description.map(str::to_string),
+            )
+        })
+        .collect();
+
+    for format in FileFormat::all() {
+        let mut first = vec![];
+        write_all_records(&mut first, format.clone(), &records)
+            .unwrap_or_else(|e| panic!("{format} should write successfully: {e}"));
+
+        let mut second = vec![];
+        write_all_records(&mut second, format.clone(), &records)
+            .unwrap_or_else(|e| panic!("{format} should write successfully: {e}"));
+        assert_eq!(first, second, "{format} output is not deterministic");
+    }
+
+    assert_round_trips(&records);
+}
+
+#[test]
+fn test_currency_round_trip() {
+    let records = vec![
+        Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Salary".to_string(),
+        )
+        .with_currency("EUR")
+        .expect("Should be valid currency"),
+        Record::new(
+            1002,
+            RecordType::Transfer {
+                from_user_id: 501,
+                to_user_id: 502,
+            },
+            15000,
+            1672534800000,
+            RecordStatus::Failure,
+            None,
+        )
+        .with_currency("USD")
+        .expect("Should be valid currency"),
+    ];
+
+    assert_round_trips(&records);
+}
+
+#[test]
+fn test_files_without_currency_default_to_xxx() {
+    let csv = "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,Salary
+";
+    let text = "TX_ID: 1001
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 501
+AMOUNT: 50000
+TIMESTAMP: 1672531200000
+STATUS: SUCCESS
+";
+
+    for (data, format) in [(csv, FileFormat::Csv), (text, FileFormat::Text)] {
+        let records = read_all_records(&mut data.as_bytes(), format.clone())
+            .unwrap_or_else(|e| panic!("{format} should read successfully: {e}"));
+        assert_eq!(records[0].currency(), Record::DEFAULT_CURRENCY);
+        assert_eq!(records[0].fee(), 0);
+        assert_eq!(records[0].reference(), "");
+    }
+}
+
+#[test]
+fn test_fee_field_round_trip() {
+    let records = vec![
+        Record::new(
+            1001,
+            RecordType::Transfer {
+                from_user_id: 501,
+                to_user_id: 502,
+            },
+            15000,
+            1672534800000,
+            RecordStatus::Success,
+            "Payment for services".to_string(),
+        )
+        .with_fee(250),
+        Record::new(
+            1002,
+            RecordType::Reversal {
+                from_user_id: 502,
+                to_user_id: 501,
+                original_tx_id: 1001,
+            },
+            15000,
+            1672538400000,
+            RecordStatus::Pending,
+            None,
+        )
+        .with_fee(u64::MAX),
+        Record::new(
+            1003,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672542000000,
+            RecordStatus::Success,
+            None,
+        ),
+    ];
+
+    assert_round_trips(&records);
+}
+
+#[test]
+fn test_reference_round_trip() {
+    let record = |id: u64, reference: &str| {
+        Record::new(
+            id,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Salary".to_string(),
+        )
+        .with_reference(reference)
+    };
+    let records = vec![
+        record(1001, "RF18 5390 0754 7034"),
+        record(1002, ""),
+        record(1003, "Ref, \"quoted\"; tab\there"),
+        record(1004, "Multi\nline \\ reference"),
+    ];
+
+    assert_round_trips(&records);
+}
@@ -0,0 +1,18 @@
+#![cfg(feature = "gen")]
+
+use std::process::Command;
+
+use ypbank_converter::{FileFormat, read_all_records};
+
+#[test]
+fn test_generate_csv_reads_back() {
+    let output = Command::new(env!("CARGO_BIN_EXE_generate"))
+        .args(["--count", "100", "--seed", "7", "--format", "csv"])
+        .output()
+        .expect("Should run generate binary");
+
+    assert!(output.status.success());
+    let records = read_all_records(&mut output.stdout.as_slice(), FileFormat::Csv)
+        .expect("Should read generated records");
+    assert_eq!(records.len(), 100);
+}
@@ -0,0 +1,107 @@
+use std::{fs, process::Command};
+
+use ypbank_converter::{
+    FileFormat, Record, RecordStatus, RecordType, RecordWriter, bin_format::BinRecordWriter,
+    write_records_to_path,
+};
+
+#[test]
+fn test_inspect_binary_file() {
+    let input =
+        std::env::temp_dir().join(format!("ypbank_inspect_input_{}.bin", std::process::id()));
+    let records = vec![
+        Record::new(
+            1001,
+            RecordType::Deposit { to_user_id: 501 },
+            50000,
+            1672531200000,
+            RecordStatus::Success,
+            "Initial account funding".to_string(),
+        ),
+        Record::new(
+            1002,
+            RecordType::Withdrawal { from_user_id: 501 },
+            1000,
+            1672534800000,
+            RecordStatus::Pending,
+            None,
+        ),
+    ];
+    write_records_to_path(&input, FileFormat::Binary, &records).expect("Should write input file");
+    let length = fs::metadata(&input).expect("Should read metadata").len();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_inspect"))
+        .arg("--input")
+        .arg(&input)
+        .output()
+        .expect("Should run inspect binary");
+
+    // Truncated file is reported with offset of broken frame
+    let bytes = fs::read(&input).expect("Should read input file");
+    fs::write(&input, &bytes[..length as usize - 1]).expect("Should write truncated file");
+    let truncated = Command::new(env!("CARGO_BIN_EXE_inspect"))
+        .arg("--input")
+        .arg(&input)
+        .output()
+        .expect("Should run inspect binary");
+
+    fs::remove_file(&input).expect("Should remove input file");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("Should be correct string");
+    assert_eq!(stdout.matches("Magic: YPBN").count(), 2);
+    assert!(stdout.contains("Offset: 0x00000000"));
+    assert!(stdout.contains("TX_ID: 1001"));
+    assert!(stdout.contains("TX_ID: 1002"));
+    assert!(stdout.contains("00000000  59 50 42 4e"));
+
+    assert!(!truncated.status.success());
+    let stderr = String::from_utf8(truncated.stderr).expect("Should be correct string");
+    let second_offset = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("Offset: "))
+        .nth(1)
+        .expect("Should have second frame");
+    assert!(stderr.contains(&format!("Error at offset {second_offset}")));
+}
+
+#[test]
+fn test_inspect_checksummed_little_endian_file() {
+    let input = std::env::temp_dir().join(format!(
+        "ypbank_inspect_little_endian_{}.bin",
+        std::process::id()
+    ));
+    let records = vec![Record::new(
+        1001,
+        RecordType::Deposit { to_user_id: 501 },
+        50000,
+        1672531200000,
+        RecordStatus::Success,
+        "Initial account funding".to_string(),
+    )];
+    let mut buffer = vec![];
+    BinRecordWriter::with_checksums(true)
+        .little_endian(true)
+        .write_all(&mut buffer, &records)
+        .expect("Should write successfully");
+    fs::write(&input, &buffer).expect("Should write input file");
+
+    let inspect = |options: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_inspect"))
+            .arg("--input")
+            .arg(&input)
+            .args(options)
+            .output()
+            .expect("Should run inspect binary")
+    };
+    let default = inspect(&[]);
+    let configured = inspect(&["--checksums", "--little-endian"]);
+
+    fs::remove_file(&input).expect("Should remove input file");
+
+    assert!(!default.status.success());
+    assert!(configured.status.success());
+    let stdout = String::from_utf8(configured.stdout).expect("Should be correct string");
+    assert!(stdout.contains("TX_ID: 1001"));
+    assert!(stdout.contains("AMOUNT: 50000"));
+}
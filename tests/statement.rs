@@ -0,0 +1,74 @@
+use std::{fs, process::Command};
+
+#[test]
+fn test_statement_for_deposit_and_transfer() {
+    let input =
+        std::env::temp_dir().join(format!("ypbank_statement_input_{}.csv", std::process::id()));
+    fs::write(
+        &input,
+        r#"TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION
+1002,TRANSFER,501,502,15000,1672534800000,SUCCESS,"Payment for services, invoice #123"
+1003,WITHDRAWAL,502,0,1000,1672538400000,PENDING,"ATM withdrawal"
+1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,"Initial account funding"
+1004,DEPOSIT,0,501,7000,1672542000000,FAILURE,"Failed deposit"
+"#,
+    )
+    .expect("Should write input file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_statement"))
+        .args(["--user", "501", "--input-format", "csv"])
+        .arg("--input")
+        .arg(&input)
+        .args(["--statement-format", "csv"])
+        .output()
+        .expect("Should run statement binary");
+
+    fs::remove_file(&input).expect("Should remove input file");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("Should be correct string"),
+        r#"TIMESTAMP,TX_ID,TX_TYPE,STATUS,CHANGE,BALANCE,DESCRIPTION
+1672531200000,1001,DEPOSIT,SUCCESS,50000,50000,Initial account funding
+1672534800000,1002,TRANSFER,SUCCESS,-15000,35000,"Payment for services, invoice #123"
+1672542000000,1004,DEPOSIT,FAILURE,0,35000,Failed deposit
+"#
+    )
+}
+
+#[test]
+fn test_statement_for_amount_above_i64_max() {
+    let input = std::env::temp_dir().join(format!(
+        "ypbank_statement_huge_input_{}.csv",
+        std::process::id()
+    ));
+    fs::write(
+        &input,
+        format!(
+            "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION\n\
+             1001,DEPOSIT,0,501,{},1672531200000,SUCCESS,\"Huge deposit\"\n",
+            u64::MAX
+        ),
+    )
+    .expect("Should write input file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_statement"))
+        .args(["--user", "501", "--input-format", "csv"])
+        .arg("--input")
+        .arg(&input)
+        .args(["--statement-format", "csv"])
+        .output()
+        .expect("Should run statement binary");
+
+    fs::remove_file(&input).expect("Should remove input file");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("Should be correct string"),
+        format!(
+            "TIMESTAMP,TX_ID,TX_TYPE,STATUS,CHANGE,BALANCE,DESCRIPTION\n\
+             1672531200000,1001,DEPOSIT,SUCCESS,{0},{0},Huge deposit\n",
+            u64::MAX
+        )
+    )
+}